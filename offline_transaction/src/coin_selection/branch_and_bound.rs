@@ -0,0 +1,141 @@
+use std::cmp::Reverse;
+use crate::coin_selection::{CoinCandidate, Selection, SelectionTarget};
+
+/// Maximum number of branches explored before giving up.
+const TOTAL_TRIES: usize = 100_000;
+
+/// Searches a changeless selection with the Branch-and-Bound algorithm
+///
+/// This is the depth-first search used by Bitcoin Core. It explores the candidates from the
+/// largest effective value and looks for a selection whose effective value lies between
+/// `target` and `target + cost_of_change`, so that dropping the excess to the fee is cheaper than
+/// creating (and later spending) a change output. Among the matches, the selection with the
+/// lowest waste is returned.
+///
+/// # Arguments
+///
+/// * `candidates` - The UTXOs which can be selected
+/// * `target` - The amounts the selection has to fund
+///
+/// # Returns
+///
+/// * `Option<Selection>` - The changeless selection with the lowest waste, or None if there is no match
+pub(crate) fn branch_and_bound(candidates: &[CoinCandidate], target: &SelectionTarget) -> Option<Selection> {
+    let mut pool = candidates.iter()
+        .filter(|candidate| candidate.effective_value() > 0)
+        .collect::<Vec<_>>();
+    pool.sort_by_key(|candidate| Reverse(candidate.effective_value()));
+
+    let cost_of_change = target.cost_of_change;
+    let is_fee_rate_high = target.fee_rate > target.long_term_fee_rate;
+    let target = target.target as i64;
+    let upper_bound = target + cost_of_change as i64;
+
+    let mut available_value = pool.iter()
+        .map(|candidate| candidate.effective_value())
+        .sum::<i64>();
+    if available_value < target {
+        return None;
+    }
+
+    let mut current_value: i64 = 0;
+    let mut current_waste: i64 = 0;
+    let mut current_selection: Vec<usize> = Vec::new();
+    let mut best: Option<(Vec<usize>, i64)> = None;
+    let mut index = 0;
+
+    for _ in 0..TOTAL_TRIES {
+        let best_waste = best.as_ref().map_or(i64::MAX, |(_, waste)| *waste);
+        let mut backtrack = false;
+
+        if current_value + available_value < target
+            || current_value > upper_bound
+            || (current_waste > best_waste && is_fee_rate_high) {
+            backtrack = true;
+        } else if current_value >= target {
+            let waste = current_waste + (current_value - target);
+            if waste <= best_waste {
+                best = Some((current_selection.clone(), waste));
+            }
+            backtrack = true;
+        }
+
+        if backtrack {
+            let Some(&last_selected) = current_selection.last() else {
+                // Every branch has been explored
+                break;
+            };
+            // Add the omitted candidates back before exploring the omission branch of the last selected one
+            index -= 1;
+            while index > last_selected {
+                available_value += pool[index].effective_value();
+                index -= 1;
+            }
+            current_value -= pool[last_selected].effective_value();
+            current_waste -= pool[last_selected].waste();
+            current_selection.pop();
+        } else {
+            let candidate = pool[index];
+            available_value -= candidate.effective_value();
+
+            // Skip the inclusion branch if an equivalent previous candidate was omitted
+            let include = match (current_selection.last(), index.checked_sub(1)) {
+                (Some(&last_selected), Some(previous)) => {
+                    last_selected == previous
+                        || candidate.effective_value() != pool[previous].effective_value()
+                        || candidate.fee != pool[previous].fee
+                },
+                _ => true,
+            };
+            if include {
+                current_selection.push(index);
+                current_value += candidate.effective_value();
+                current_waste += candidate.waste();
+            }
+        }
+        index += 1;
+    }
+
+    best.map(|(selection, waste)| Selection {
+        indices: selection.into_iter()
+            .map(|index| pool[index].index)
+            .collect(),
+        changeless: true,
+        algorithm: "branchAndBound".to_string(),
+        reason: format!(
+            "found a changeless selection within the cost of change of {} sat (waste {} sat)",
+            cost_of_change, waste),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::coin_selection::fixtures::{candidates, target};
+    use super::*;
+
+    #[test]
+    fn test_branch_and_bound_exact_match() {
+        let candidates = candidates(&[100_068, 200_068, 300_068, 500_068], 68);
+        let selection = branch_and_bound(&candidates, &target(600_000, 100, 0)).unwrap();
+        let mut indices = selection.indices;
+        indices.sort();
+        assert!(indices == vec![0, 3] || indices == vec![0, 1, 2]);
+        assert!(selection.reason.contains("waste 0 sat"));
+    }
+
+    #[test]
+    fn test_branch_and_bound_within_cost_of_change() {
+        let candidates = candidates(&[100_068, 250_068], 68);
+        let selection = branch_and_bound(&candidates, &target(249_950, 100, 0)).unwrap();
+        assert_eq!(selection.indices, vec![1]);
+        assert!(selection.reason.contains("waste 50 sat"));
+    }
+
+    #[test]
+    fn test_branch_and_bound_no_match() {
+        let candidates = candidates(&[100_068, 250_068], 68);
+        assert!(branch_and_bound(&candidates, &target(200_000, 100, 0)).is_none());
+        assert!(branch_and_bound(&candidates, &target(1_000_000, 100, 0)).is_none());
+    }
+}
@@ -0,0 +1,156 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::coin_selection::{CoinCandidate, Selection, SelectionTarget};
+
+/// Number of random passes of the approximate subset search.
+const ITERATIONS: usize = 1_000;
+
+
+/// Selects candidates with the knapsack solver of Bitcoin Core's legacy wallet
+///
+/// A candidate matching the target exactly, or every candidate smaller than the target plus the
+/// minimum change if they sum to the target exactly, is selected without change. Otherwise, the
+/// smallest subset of the smaller candidates funding the target and a change output is
+/// approximated by random passes, and replaced by the lowest single larger candidate if that
+/// one is not worse.
+///
+/// # Arguments
+///
+/// * `candidates` - The UTXOs which can be selected
+/// * `target` - The amounts the selection has to fund
+/// * `rng` - Random number generator for the approximate subset search
+///
+/// # Returns
+///
+/// * `Option<Selection>` - The selection, or None if the candidates are insufficient
+pub(crate) fn knapsack<R: Rng>(candidates: &[CoinCandidate],
+                               target: &SelectionTarget,
+                               rng: &mut R) -> Option<Selection> {
+    let mut pool = candidates.iter()
+        .filter(|candidate| candidate.effective_value() > 0)
+        .collect::<Vec<_>>();
+    pool.shuffle(rng);
+
+    let exact_target = target.target as i64;
+    let change_target = (target.target + target.minimum_change) as i64;
+    let selection = |indices: Vec<usize>, changeless: bool, reason: &str| Selection {
+        indices,
+        changeless,
+        algorithm: "knapsack".to_string(),
+        reason: reason.to_string(),
+    };
+
+    if let Some(candidate) = pool.iter().find(|candidate| candidate.effective_value() == exact_target) {
+        return Some(selection(vec![candidate.index], true, "a single UTXO matched the target exactly"));
+    }
+
+    let (mut smaller, larger): (Vec<_>, Vec<_>) = pool.into_iter()
+        .partition(|candidate| candidate.effective_value() < change_target);
+    let lowest_larger = larger.into_iter()
+        .min_by_key(|candidate| candidate.effective_value());
+
+    let total_smaller = smaller.iter()
+        .map(|candidate| candidate.effective_value())
+        .sum::<i64>();
+    if total_smaller == exact_target {
+        return Some(selection(
+            smaller.iter().map(|candidate| candidate.index).collect(),
+            true,
+            "the UTXOs smaller than the target matched it exactly"));
+    }
+    if total_smaller < change_target {
+        return lowest_larger.map(|candidate| selection(
+            vec![candidate.index],
+            false,
+            "the UTXOs smaller than the target were insufficient, selected the lowest larger UTXO"));
+    }
+
+    smaller.sort_by_key(|candidate| std::cmp::Reverse(candidate.effective_value()));
+    let mut best_included = vec![true; smaller.len()];
+    let mut best_value = total_smaller;
+
+    for _ in 0..ITERATIONS {
+        if best_value == change_target {
+            break;
+        }
+        let mut included = vec![false; smaller.len()];
+        let mut value: i64 = 0;
+        let mut reached = false;
+        // The first pass includes each candidate randomly, the second one fills the gap in order
+        for pass in 0..2 {
+            if reached {
+                break;
+            }
+            for (index, candidate) in smaller.iter().enumerate() {
+                let include = if pass == 0 { rng.gen_bool(0.5) } else { !included[index] };
+                if !include || included[index] {
+                    continue;
+                }
+                value += candidate.effective_value();
+                included[index] = true;
+                if value >= change_target {
+                    reached = true;
+                    if value < best_value {
+                        best_value = value;
+                        best_included = included.clone();
+                    }
+                    value -= candidate.effective_value();
+                    included[index] = false;
+                }
+            }
+        }
+    }
+
+    if let Some(candidate) = lowest_larger.filter(|candidate| candidate.effective_value() <= best_value) {
+        return Some(selection(
+            vec![candidate.index],
+            false,
+            "the lowest larger UTXO was closer to the target than any subset of smaller UTXOs"));
+    }
+
+    Some(selection(
+        smaller.iter()
+            .zip(best_included)
+            .filter(|(_, included)| *included)
+            .map(|(candidate, _)| candidate.index)
+            .collect(),
+        false,
+        "approximated the smallest subset of smaller UTXOs funding the target and change"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::coin_selection::fixtures::{candidates, target};
+    use super::*;
+
+    #[test]
+    fn test_knapsack_exact_match() {
+        let candidates = candidates(&[10_000, 25_000, 40_000], 0);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let selection = knapsack(&candidates, &target(25_000, 100, 1_000), &mut rng).unwrap();
+        assert_eq!(selection.indices, vec![1]);
+        assert!(selection.changeless);
+    }
+
+    #[test]
+    fn test_knapsack_subset_and_lowest_larger() {
+        let candidates = candidates(&[6_000, 5_000, 4_000, 100_000], 0);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // 5_000 + 4_000 funds the target and the minimum change exactly
+        let selection = knapsack(&candidates, &target(8_000, 100, 1_000), &mut rng).unwrap();
+        let mut indices = selection.indices;
+        indices.sort();
+        assert_eq!(indices, vec![1, 2]);
+        assert!(!selection.changeless);
+
+        let selection = knapsack(&candidates, &target(20_000, 100, 1_000), &mut rng).unwrap();
+        assert_eq!(selection.indices, vec![3]);
+
+        assert!(knapsack(&candidates, &target(200_000, 100, 1_000), &mut rng).is_none());
+    }
+}
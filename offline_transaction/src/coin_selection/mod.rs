@@ -0,0 +1,400 @@
+use std::fmt;
+use std::str::FromStr;
+use bitcoin::{Amount, Script, TxOut};
+use serde::{Deserialize, Serialize};
+use crate::errors::OfflineTransactionError::{InsufficientFunds, InvalidTransaction, UnsupportedError};
+use crate::errors::{OfflineTransactionError, OfflineTransactionResult};
+use crate::transaction::report::{CoinSelectionReport, SelectedUtxo, StrategyWaste};
+use crate::frozen::FrozenRegistry;
+use crate::types::amount::checked_sum;
+use crate::types::bitcoin::BitcoinTransactionData;
+use crate::utils::fee_calculator::{base_vsize, change_cost, dust_limit, input_vsize};
+use crate::utils::str_to_address::str_to_address;
+
+pub(crate) mod branch_and_bound;
+pub(crate) mod knapsack;
+pub(crate) mod single_random_draw;
+pub mod strategies;
+
+/// Default fee rate (sat/vB) expected to spend UTXOs in the long term, used to weigh the cost of change.
+pub(crate) const LONG_TERM_FEE_RATE: u64 = 10;
+
+/// A strategy selecting the inputs of a transaction from a UTXO pool
+///
+/// Implement this trait to plug a custom coin selection into
+/// [`Platform::generate_unsigned_transaction_with`](crate::Platform::generate_unsigned_transaction_with).
+pub trait CoinSelectionStrategy {
+    /// Name of the strategy displayed in the coin selection report
+    fn name(&self) -> &'static str;
+
+    /// Selects the UTXOs funding the target
+    ///
+    /// # Arguments
+    ///
+    /// * `candidates` - The UTXOs of the pool which can be selected
+    /// * `target` - The amounts the selection has to fund
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Selection>` - The selected UTXOs, or None if the candidates cannot fund the target
+    fn select(&self, candidates: &[CoinCandidate], target: &SelectionTarget) -> Option<Selection>;
+}
+
+/// A UTXO of the pool which can be selected
+///
+/// # Fields
+///
+/// * `index` - Index of the UTXO in the pool
+/// * `amount` - Amount of the UTXO in satoshis
+/// * `fee` - Fee to spend the UTXO at the transaction fee rate
+/// * `long_term_fee` - Fee to spend the UTXO at the long term fee rate
+/// * `confirmations` - Number of confirmations of the UTXO (0 if unknown)
+/// * `address` - Address having the UTXO
+/// * `cluster` - Cluster of the UTXO, the address if the UTXO has no cluster
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoinCandidate {
+    pub index: usize,
+    pub amount: u64,
+    pub fee: u64,
+    pub long_term_fee: u64,
+    pub confirmations: u32,
+    pub address: String,
+    pub cluster: String,
+}
+
+/// The amounts a coin selection has to fund
+///
+/// # Fields
+///
+/// * `target` - Amount of the outputs plus the fee of the transaction excluding its inputs
+/// * `cost_of_change` - Cost of creating a change output now and spending it later
+/// * `minimum_change` - Smallest excess over the target funding a change output which is neither
+///   dust nor cheaper than the cost of change
+/// * `fee_rate` - Fee rate of the transaction (sat/vB)
+/// * `long_term_fee_rate` - Fee rate expected to spend the UTXOs later (sat/vB)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionTarget {
+    pub target: u64,
+    pub cost_of_change: u64,
+    pub minimum_change: u64,
+    pub fee_rate: u64,
+    pub long_term_fee_rate: u64,
+}
+
+/// Result of a coin selection strategy
+///
+/// # Fields
+///
+/// * `indices` - Pool indices ([`CoinCandidate::index`]) of the selected UTXOs
+/// * `changeless` - Whether the selection is funded without a change output, in which case
+///   the excess over the target is left to the fee
+/// * `algorithm` - The algorithm which found the selection
+/// * `reason` - Why the UTXOs were selected
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub indices: Vec<usize>,
+    pub changeless: bool,
+    pub algorithm: String,
+    pub reason: String,
+}
+
+/// The built-in coin selection strategies which can be chosen in the input JSON
+///
+/// # Variants
+///
+/// * `Auto` - Branch-and-Bound for a changeless selection with a single random draw fallback
+/// * `LargestFirst` - The largest UTXOs first to minimise the number of inputs
+/// * `OldestFirst` - The UTXOs with the most confirmations first
+/// * `SmallestFirst` - The smallest UTXOs first to consolidate them while fees are low
+/// * `Knapsack` - Bitcoin Core's legacy knapsack solver
+/// * `Privacy` - UTXOs of a single cluster, spending the UTXOs of a reused address together
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CoinSelectionMethod {
+    #[default]
+    Auto,
+    LargestFirst,
+    OldestFirst,
+    SmallestFirst,
+    Knapsack,
+    Privacy,
+}
+
+impl CoinCandidate {
+    /// Amount of the UTXO minus the fee to spend it
+    pub fn effective_value(&self) -> i64 {
+        self.amount as i64 - self.fee as i64
+    }
+
+    /// Extra fee paid by spending the UTXO now rather than at the long term fee rate
+    pub fn waste(&self) -> i64 {
+        self.fee as i64 - self.long_term_fee as i64
+    }
+}
+
+impl CoinSelectionMethod {
+    /// Every built-in strategy, in the order of the waste comparison
+    pub const ALL: [CoinSelectionMethod; 6] = [
+        CoinSelectionMethod::Auto,
+        CoinSelectionMethod::LargestFirst,
+        CoinSelectionMethod::OldestFirst,
+        CoinSelectionMethod::SmallestFirst,
+        CoinSelectionMethod::Knapsack,
+        CoinSelectionMethod::Privacy,
+    ];
+
+    /// Returns the strategy implementing the method
+    pub fn strategy(&self) -> Box<dyn CoinSelectionStrategy> {
+        match self {
+            CoinSelectionMethod::Auto => Box::new(strategies::Auto),
+            CoinSelectionMethod::LargestFirst => Box::new(strategies::LargestFirst),
+            CoinSelectionMethod::OldestFirst => Box::new(strategies::OldestFirst),
+            CoinSelectionMethod::SmallestFirst => Box::new(strategies::SmallestFirst),
+            CoinSelectionMethod::Knapsack => Box::new(strategies::Knapsack),
+            CoinSelectionMethod::Privacy => Box::new(strategies::Privacy),
+        }
+    }
+}
+
+impl fmt::Display for CoinSelectionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.strategy().name())
+    }
+}
+
+impl FromStr for CoinSelectionMethod {
+    type Err = OfflineTransactionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CoinSelectionMethod::ALL.into_iter()
+            .find(|method| method.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| UnsupportedError {
+                component: "coin selection".to_string(),
+                input: s.to_string(),
+                expected: CoinSelectionMethod::ALL.iter()
+                    .map(|method| method.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+    }
+}
+
+/// Calculates the waste of a selection
+///
+/// The waste is the extra fee paid by spending the inputs now rather than at the long term
+/// fee rate, plus the cost of the change output, or the excess left to the fee if the
+/// selection is changeless.
+pub(crate) fn selection_waste(candidates: &[CoinCandidate], selection: &Selection, target: &SelectionTarget) -> i64 {
+    let selected = candidates.iter()
+        .filter(|candidate| selection.indices.contains(&candidate.index))
+        .collect::<Vec<_>>();
+    let inputs_waste = selected.iter()
+        .map(|candidate| candidate.waste())
+        .sum::<i64>();
+
+    if selection.changeless {
+        let selected_value = selected.iter()
+            .map(|candidate| candidate.effective_value())
+            .sum::<i64>();
+        inputs_waste + selected_value - target.target as i64
+    } else {
+        inputs_waste + target.cost_of_change as i64
+    }
+}
+
+/// Selects the inputs of a transaction from the UTXO pool
+///
+/// The selection of the given strategy is used, and the selections of every built-in strategy
+/// are compared by their waste in the report.
+///
+/// # Arguments
+///
+/// * `params` - The transaction data containing the UTXO pool
+/// * `frozen` - The frozen UTXOs, which are never selected
+/// * `outputs` - Outputs of the transaction (without change)
+/// * `change_script` - script_pubkey of the change output
+/// * `fee_rate` - Fee rate in satoshis per virtual byte
+/// * `strategy` - The coin selection strategy
+///
+/// # Returns
+///
+/// * `OfflineTransactionResult<(Vec<usize>, CoinSelectionReport)>` - Indices of the selected UTXOs in
+///   the pool and a report of the selection, or an error if the pool cannot be parsed or does not
+///   fund the outputs
+pub(crate) fn select_coins(params: &BitcoinTransactionData,
+                           frozen: &FrozenRegistry,
+                           outputs: &[TxOut],
+                           change_script: &Script,
+                           fee_rate: u64,
+                           strategy: &dyn CoinSelectionStrategy)
+    -> OfflineTransactionResult<(Vec<usize>, CoinSelectionReport)> {
+    let fee = |vsize: u64, fee_rate: u64| vsize.checked_mul(fee_rate)
+        .ok_or_else(|| InvalidTransaction {
+            reason: format!("fee overflows at fee rate {} sat/vB", fee_rate),
+        });
+
+    let utxos = params.utxos();
+    let long_term_fee_rate = params.long_term_fee_rate();
+    let candidates = utxos.iter()
+        .enumerate()
+        .filter(|(_, utxo)| !frozen.is_frozen(utxo.txid(), utxo.vout()))
+        .map(|(index, utxo)| {
+            let script_pubkey = str_to_address(utxo.address(), params.network(), &format!("utxos[{}].address", index))?
+                .script_pubkey();
+            let vsize = input_vsize(&script_pubkey);
+            let amount = utxo.amount().resolve(&format!("utxos[{}].amount", index), params.amount_unit())?;
+            Ok(CoinCandidate {
+                index,
+                amount: amount.to_sat(),
+                fee: fee(vsize, fee_rate)?,
+                long_term_fee: fee(vsize, long_term_fee_rate)?,
+                confirmations: utxo.confirmations().unwrap_or_default(),
+                address: utxo.address().to_string(),
+                cluster: utxo.cluster().unwrap_or(utxo.address()).to_string(),
+            })
+        })
+        .collect::<OfflineTransactionResult<Vec<_>>>()?;
+
+    let total_output_amount = checked_sum(outputs.iter().map(|output| output.value), "output amounts")?;
+    let base_fee = Amount::from_sat(fee(base_vsize(outputs), fee_rate)?);
+    let (change_fee, cost_of_change) = change_cost(change_script, fee_rate, long_term_fee_rate)?;
+    let target = SelectionTarget {
+        target: checked_sum([total_output_amount, base_fee], "output amounts and the fee")?.to_sat(),
+        cost_of_change,
+        // The change has to be worth more than the cost of change, and not be dust
+        minimum_change: (change_fee + dust_limit(change_script)).max(cost_of_change + 1),
+        fee_rate,
+        long_term_fee_rate,
+    };
+
+    let selected = strategy.select(&candidates, &target);
+    let comparison = CoinSelectionMethod::ALL.iter()
+        .map(|method| {
+            // Reuse the selection of the chosen strategy so that random draws match the transaction
+            let selection = if method.to_string() == strategy.name() {
+                selected.clone()
+            } else {
+                method.strategy().select(&candidates, &target)
+            };
+            StrategyWaste {
+                strategy: method.to_string(),
+                inputs: selection.as_ref().map(|selection| selection.indices.len()),
+                changeless: selection.as_ref().map(|selection| selection.changeless),
+                waste: selection.as_ref().map(|selection| selection_waste(&candidates, selection, &target)),
+            }
+        })
+        .collect();
+
+    let Some(selection) = selected else {
+        // Only the UTXOs worth more than their fee are spent
+        let spendable = candidates.iter()
+            .filter(|candidate| candidate.effective_value() > 0)
+            .collect::<Vec<_>>();
+        let total_in = checked_sum(spendable.iter().map(|candidate| Amount::from_sat(candidate.amount)), "UTXO amounts")?;
+        let fee = checked_sum(
+            spendable.iter()
+                .map(|candidate| Amount::from_sat(candidate.fee))
+                .chain([base_fee]),
+            "fees")?;
+        let required = checked_sum([total_output_amount, fee], "output amounts and the fee")?;
+        return Err(InsufficientFunds {
+            shortfall: required.checked_sub(total_in).unwrap_or(Amount::ZERO),
+            total_in,
+            total_out: total_output_amount,
+            fee,
+        });
+    };
+
+    // In selection order, which is the order of the inputs
+    let selected_candidates = selection.indices.iter()
+        .filter_map(|index| candidates.iter().find(|candidate| candidate.index == *index))
+        .collect::<Vec<_>>();
+    let mut clusters: Vec<String> = Vec::new();
+    for candidate in &selected_candidates {
+        if !clusters.contains(&candidate.cluster) {
+            clusters.push(candidate.cluster.clone());
+        }
+    }
+
+    let report = CoinSelectionReport {
+        strategy: strategy.name().to_string(),
+        algorithm: selection.algorithm.clone(),
+        reason: selection.reason.clone(),
+        changeless: selection.changeless,
+        waste: selection_waste(&candidates, &selection, &target),
+        selected: selected_candidates.iter()
+            .map(|candidate| {
+                SelectedUtxo {
+                    txid: utxos[candidate.index].txid().to_string(),
+                    vout: utxos[candidate.index].vout(),
+                    amount: candidate.amount,
+                    effective_value: candidate.effective_value(),
+                }
+            })
+            .collect(),
+        clusters,
+        comparison,
+    };
+
+    Ok((selection.indices, report))
+}
+
+
+/// Fixtures shared by the tests of the coin selection algorithms
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::{CoinCandidate, SelectionTarget};
+
+    /// Builds unconfirmed candidates of the amounts, each spent at the same fee now and in the long term
+    pub(crate) fn candidates(amounts: &[u64], fee: u64) -> Vec<CoinCandidate> {
+        amounts.iter()
+            .enumerate()
+            .map(|(index, amount)| CoinCandidate {
+                index, amount: *amount, fee, long_term_fee: fee, confirmations: 0, ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Builds a target at 1 sat/vB now and in the long term
+    pub(crate) fn target(target: u64, cost_of_change: u64, minimum_change: u64) -> SelectionTarget {
+        SelectionTarget { target, cost_of_change, minimum_change, fee_rate: 1, long_term_fee_rate: 1 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coin_selection_method_from_str() {
+        assert_eq!(CoinSelectionMethod::from_str("knapsack").unwrap(), CoinSelectionMethod::Knapsack);
+        assert_eq!(CoinSelectionMethod::from_str("LargestFirst").unwrap(), CoinSelectionMethod::LargestFirst);
+        assert!(CoinSelectionMethod::from_str("random").is_err());
+
+        let method: CoinSelectionMethod = serde_json::from_str(r#""oldestFirst""#).unwrap();
+        assert_eq!(method, CoinSelectionMethod::OldestFirst);
+        assert_eq!(method.to_string(), "oldestFirst");
+    }
+
+    #[test]
+    fn test_selection_waste() {
+        let candidates = [100_000, 60_000]
+            .iter()
+            .enumerate()
+            .map(|(index, amount)| CoinCandidate {
+                index, amount: *amount, fee: 100, long_term_fee: 40, confirmations: 0, ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let target = SelectionTarget {
+            target: 59_800, cost_of_change: 500, minimum_change: 800, fee_rate: 5, long_term_fee_rate: 2,
+        };
+        let selection = |indices: Vec<usize>, changeless: bool| Selection {
+            indices, changeless, algorithm: String::new(), reason: String::new(),
+        };
+
+        // 60 sat spent over the long term fee plus 100 sat excess
+        assert_eq!(selection_waste(&candidates, &selection(vec![1], true), &target), 160);
+        // 60 sat spent over the long term fee plus the cost of change
+        assert_eq!(selection_waste(&candidates, &selection(vec![0], false), &target), 560);
+    }
+}
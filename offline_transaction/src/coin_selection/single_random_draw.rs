@@ -0,0 +1,72 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::coin_selection::{CoinCandidate, Selection, SelectionTarget};
+
+/// Selects candidates in random order until the target and a change output are funded
+///
+/// # Arguments
+///
+/// * `candidates` - The UTXOs which can be selected
+/// * `target` - The amounts the selection has to fund
+/// * `rng` - Random number generator to shuffle the candidates
+///
+/// # Returns
+///
+/// * `Option<Selection>` - The selection with a change output, or None if the candidates are insufficient
+pub(crate) fn single_random_draw<R: Rng>(candidates: &[CoinCandidate],
+                                         target: &SelectionTarget,
+                                         rng: &mut R) -> Option<Selection> {
+    let mut pool = candidates.iter()
+        .filter(|candidate| candidate.effective_value() > 0)
+        .collect::<Vec<_>>();
+    pool.shuffle(rng);
+
+    let required = (target.target + target.minimum_change) as i64;
+    let mut selected_value: i64 = 0;
+    let mut indices = Vec::new();
+
+    for candidate in pool {
+        selected_value += candidate.effective_value();
+        indices.push(candidate.index);
+
+        if selected_value >= required {
+            return Some(Selection {
+                indices,
+                changeless: false,
+                algorithm: "singleRandomDraw".to_string(),
+                reason: format!(
+                    "no changeless selection within the cost of change of {} sat, \
+                     selected random UTXOs until the outputs, fee and change were funded",
+                    target.cost_of_change),
+            });
+        }
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::coin_selection::fixtures::{candidates, target};
+    use super::*;
+
+    #[test]
+    fn test_single_random_draw() {
+        let candidates = candidates(&[30_000, 50_000, 70_000, 100], 68);
+        let mut target = target(60_000, 100, 1_000);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let selection = single_random_draw(&candidates, &target, &mut rng).unwrap();
+        let selected_value = selection.indices.iter()
+            .map(|index| candidates[*index].effective_value())
+            .sum::<i64>();
+        assert!(selected_value >= 61_000);
+        assert!(!selection.changeless);
+
+        target.target = 150_000;
+        assert!(single_random_draw(&candidates, &target, &mut rng).is_none());
+    }
+}
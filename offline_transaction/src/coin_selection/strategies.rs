@@ -0,0 +1,295 @@
+//! Built-in coin selection strategies
+//!
+//! Every strategy can be chosen with [`CoinSelectionMethod`](crate::coin_selection::CoinSelectionMethod)
+//! in the input JSON, or passed directly to
+//! [`Platform::generate_unsigned_transaction_with`](crate::Platform::generate_unsigned_transaction_with).
+
+use std::cmp::Reverse;
+use crate::coin_selection::{selection_waste, CoinCandidate, CoinSelectionStrategy, Selection, SelectionTarget};
+use crate::coin_selection::branch_and_bound::branch_and_bound;
+use crate::coin_selection::knapsack::knapsack;
+use crate::coin_selection::single_random_draw::single_random_draw;
+
+/// Branch-and-Bound for a changeless selection with a single random draw fallback
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Auto;
+
+/// Selects the largest UTXOs first to minimise the number of inputs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LargestFirst;
+
+/// Selects the UTXOs with the most confirmations first
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OldestFirst;
+
+/// Selects the smallest UTXOs first to consolidate them while fees are low
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmallestFirst;
+
+/// Bitcoin Core's legacy knapsack solver
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Knapsack;
+
+/// Avoids linking clusters of UTXOs on-chain
+///
+/// The UTXOs of a reused address are always spent together, since the address already links
+/// them. A selection from a single cluster is preferred, choosing the cluster with the lowest
+/// waste. If no cluster funds the transaction alone, the fewest clusters with the most value are merged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Privacy;
+
+impl CoinSelectionStrategy for Auto {
+    fn name(&self) -> &'static str {
+        "auto"
+    }
+
+    fn select(&self, candidates: &[CoinCandidate], target: &SelectionTarget) -> Option<Selection> {
+        branch_and_bound(candidates, target)
+            .or_else(|| single_random_draw(candidates, target, &mut rand::thread_rng()))
+    }
+}
+
+impl CoinSelectionStrategy for LargestFirst {
+    fn name(&self) -> &'static str {
+        "largestFirst"
+    }
+
+    fn select(&self, candidates: &[CoinCandidate], target: &SelectionTarget) -> Option<Selection> {
+        let mut ordered = candidates.iter().collect::<Vec<_>>();
+        ordered.sort_by_key(|candidate| Reverse(candidate.effective_value()));
+
+        accumulate(self.name(), "selected the largest UTXOs first", ordered, target)
+    }
+}
+
+impl CoinSelectionStrategy for OldestFirst {
+    fn name(&self) -> &'static str {
+        "oldestFirst"
+    }
+
+    fn select(&self, candidates: &[CoinCandidate], target: &SelectionTarget) -> Option<Selection> {
+        let mut ordered = candidates.iter().collect::<Vec<_>>();
+        ordered.sort_by_key(|candidate| Reverse(candidate.confirmations));
+
+        accumulate(self.name(), "selected the UTXOs with the most confirmations first", ordered, target)
+    }
+}
+
+impl CoinSelectionStrategy for SmallestFirst {
+    fn name(&self) -> &'static str {
+        "smallestFirst"
+    }
+
+    fn select(&self, candidates: &[CoinCandidate], target: &SelectionTarget) -> Option<Selection> {
+        let mut ordered = candidates.iter().collect::<Vec<_>>();
+        ordered.sort_by_key(|candidate| candidate.effective_value());
+
+        accumulate(self.name(), "selected the smallest UTXOs first", ordered, target)
+    }
+}
+
+impl CoinSelectionStrategy for Knapsack {
+    fn name(&self) -> &'static str {
+        "knapsack"
+    }
+
+    fn select(&self, candidates: &[CoinCandidate], target: &SelectionTarget) -> Option<Selection> {
+        knapsack(candidates, target, &mut rand::thread_rng())
+    }
+}
+
+impl CoinSelectionStrategy for Privacy {
+    fn name(&self) -> &'static str {
+        "privacy"
+    }
+
+    fn select(&self, candidates: &[CoinCandidate], target: &SelectionTarget) -> Option<Selection> {
+        // Each address becomes a single candidate spending all of its UTXOs
+        let mut address_groups: Vec<Vec<&CoinCandidate>> = Vec::new();
+        for candidate in candidates {
+            match address_groups.iter_mut().find(|group| group[0].address == candidate.address) {
+                Some(group) => group.push(candidate),
+                None => address_groups.push(vec![candidate]),
+            }
+        }
+        let groups = address_groups.iter()
+            .enumerate()
+            .map(|(index, group)| CoinCandidate {
+                index,
+                amount: group.iter().map(|candidate| candidate.amount).sum(),
+                fee: group.iter().map(|candidate| candidate.fee).sum(),
+                long_term_fee: group.iter().map(|candidate| candidate.long_term_fee).sum(),
+                confirmations: group.iter().map(|candidate| candidate.confirmations).min().unwrap_or_default(),
+                address: group[0].address.clone(),
+                cluster: group[0].cluster.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut clusters: Vec<(&str, Vec<CoinCandidate>)> = Vec::new();
+        for group in &groups {
+            match clusters.iter_mut().find(|(cluster, _)| *cluster == group.cluster) {
+                Some((_, cluster_groups)) => cluster_groups.push(group.clone()),
+                None => clusters.push((&group.cluster, vec![group.clone()])),
+            }
+        }
+
+        let single_cluster = clusters.iter()
+            .filter_map(|(cluster, cluster_groups)| {
+                Auto.select(cluster_groups, target)
+                    .map(|selection| (selection_waste(&groups, &selection, target), *cluster, selection))
+            })
+            .min_by_key(|(waste, _, _)| *waste);
+
+        let (selection, reason) = match single_cluster {
+            Some((_, cluster, selection)) => {
+                let reason = format!("spent from the single cluster {}, {}", cluster, selection.reason);
+                (selection, reason)
+            },
+            None => {
+                let cluster_value = |cluster_groups: &[CoinCandidate]| cluster_groups.iter()
+                    .map(|group| group.effective_value().max(0))
+                    .sum::<i64>();
+                clusters.sort_by_key(|(_, cluster_groups)| std::cmp::Reverse(cluster_value(cluster_groups)));
+
+                let required = (target.target + target.minimum_change) as i64;
+                let mut merged_value: i64 = 0;
+                let mut merged = Vec::new();
+                let mut merged_groups = Vec::new();
+                for (cluster, cluster_groups) in &clusters {
+                    merged_value += cluster_value(cluster_groups);
+                    merged.push(*cluster);
+                    merged_groups.extend(cluster_groups.iter().cloned());
+                    if merged_value >= required {
+                        break;
+                    }
+                }
+
+                let selection = Auto.select(&merged_groups, target)?;
+                let reason = format!("no single cluster funds the transaction, merged the clusters {}, {}",
+                                     merged.join(", "), selection.reason);
+                (selection, reason)
+            }
+        };
+
+        Some(Selection {
+            indices: selection.indices.iter()
+                .flat_map(|index| address_groups[*index].iter().map(|candidate| candidate.index))
+                .collect(),
+            changeless: selection.changeless,
+            algorithm: selection.algorithm,
+            reason,
+        })
+    }
+}
+
+/// Selects candidates in the given order until the target is funded
+///
+/// UTXOs costing more to spend than they are worth are skipped. The selection is changeless
+/// if the excess over the target does not exceed the cost of change, otherwise candidates are
+/// added until a change output is funded as well.
+fn accumulate(algorithm: &str,
+              reason: &str,
+              ordered: Vec<&CoinCandidate>,
+              target: &SelectionTarget) -> Option<Selection> {
+    let exact_target = target.target as i64;
+    let mut selected_value: i64 = 0;
+    let mut indices = Vec::new();
+
+    for candidate in ordered.into_iter().filter(|candidate| candidate.effective_value() > 0) {
+        selected_value += candidate.effective_value();
+        indices.push(candidate.index);
+
+        let excess = selected_value - exact_target;
+        let changeless = (0..=target.cost_of_change as i64).contains(&excess);
+        if changeless || excess >= target.minimum_change as i64 {
+            return Some(Selection {
+                indices,
+                changeless,
+                algorithm: algorithm.to_string(),
+                reason: reason.to_string(),
+            });
+        }
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::coin_selection::fixtures::{candidates, target};
+    use super::*;
+
+    #[test]
+    fn test_ordered_strategies() {
+        let mut candidates = candidates(&[20_100, 50_100, 10_100, 80], 100);
+        for (candidate, confirmations) in candidates.iter_mut().zip([3, 1, 100, 200]) {
+            candidate.confirmations = confirmations;
+        }
+
+        let largest = LargestFirst.select(&candidates, &target(40_000, 200, 1_000)).unwrap();
+        assert_eq!(largest.indices, vec![1]);
+        assert!(!largest.changeless);
+
+        let oldest = OldestFirst.select(&candidates, &target(40_000, 200, 1_000)).unwrap();
+        assert_eq!(oldest.indices, vec![2, 0, 1]);
+
+        let smallest = SmallestFirst.select(&candidates, &target(30_000, 200, 1_000)).unwrap();
+        assert_eq!(smallest.indices, vec![2, 0]);
+        assert!(smallest.changeless);
+
+        assert!(LargestFirst.select(&candidates, &target(100_000, 200, 1_000)).is_none());
+    }
+
+    fn clustered_candidates(utxos: &[(u64, &str, &str)]) -> Vec<CoinCandidate> {
+        utxos.iter()
+            .enumerate()
+            .map(|(index, (amount, address, cluster))| CoinCandidate {
+                index, amount: *amount, fee: 100, long_term_fee: 100, confirmations: 0,
+                address: address.to_string(), cluster: cluster.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_privacy_prefers_single_cluster() {
+        let candidates = clustered_candidates(&[
+            (30_100, "a1", "alice"),
+            (30_100, "b1", "bob"),
+            (20_100, "a2", "alice"),
+            (60_100, "b2", "bob"),
+        ]);
+
+        let selection = Privacy.select(&candidates, &target(45_000, 200, 1_000)).unwrap();
+        assert!(selection.indices.iter().all(|index| candidates[*index].cluster == "alice")
+            || selection.indices.iter().all(|index| candidates[*index].cluster == "bob"));
+    }
+
+    #[test]
+    fn test_privacy_spends_reused_address_together() {
+        let candidates = clustered_candidates(&[
+            (50_100, "a1", "alice"),
+            (10_100, "a2", "alice"),
+            (10_100, "a2", "alice"),
+        ]);
+
+        let selection = Privacy.select(&candidates, &target(15_000, 200, 1_000)).unwrap();
+        let mut indices = selection.indices;
+        indices.sort();
+        // The UTXOs of a2 are never spent separately
+        assert!(indices == vec![1, 2] || indices == vec![0]);
+    }
+
+    #[test]
+    fn test_privacy_merges_clusters_when_needed() {
+        let candidates = clustered_candidates(&[
+            (30_100, "a1", "alice"),
+            (30_100, "b1", "bob"),
+            (1_100, "c1", "carol"),
+        ]);
+
+        let selection = Privacy.select(&candidates, &target(50_000, 200, 1_000)).unwrap();
+        assert!(selection.reason.contains("merged the clusters alice, bob"));
+        assert!(!selection.indices.contains(&2));
+    }
+}
@@ -1,3 +1,4 @@
+use bitcoin::Amount;
 use thiserror::Error;
 
 
@@ -38,6 +39,12 @@ pub type OfflineTransactionResult<T> = Result<T, OfflineTransactionError>;
 /// * `ParseError` - Failed to parse data from one format to another
 /// * `UnsupportedError` - The provided input is not supported for the given component
 /// * `HasherError` - An error occurred during hashing operations
+/// * `MissingParameter` - A parameter required by the requested operation is not specified
+/// * `InvalidTransaction` - The transaction cannot be built from the given parameters
+/// * `StaleFeeSnapshot` - The fee snapshot is older than the accepted age
+/// * `FutureFeeSnapshot` - The fee snapshot is dated in the future beyond the accepted clock skew
+/// * `FrozenUtxo` - The request spends a UTXO of the frozen UTXO registry
+/// * `InsufficientFunds` - The inputs cannot pay the outputs and the fee, short by `shortfall`
 #[derive(Error, Debug)]
 pub enum OfflineTransactionError {
     #[error("{path}")]
@@ -68,4 +75,38 @@ pub enum OfflineTransactionError {
     },
     #[error("{0}")]
     HasherError(String),
+    #[error("'{parameter}' is required to {operation}")]
+    MissingParameter {
+        parameter: String,
+        operation: String,
+    },
+    #[error("{reason}")]
+    InvalidTransaction {
+        reason: String,
+    },
+    #[error("{path} is {age} seconds old, expected at most {max_age} seconds")]
+    StaleFeeSnapshot {
+        path: String,
+        age: u64,
+        max_age: u64,
+    },
+    #[error("{path} is dated {ahead} seconds in the future, expected at most {max_skew} seconds of clock skew")]
+    FutureFeeSnapshot {
+        path: String,
+        ahead: u64,
+        max_skew: u64,
+    },
+    #[error("{outpoint} is frozen: {reason}")]
+    FrozenUtxo {
+        outpoint: String,
+        reason: String,
+    },
+    #[error("insufficient funds: {} sat short, inputs of {} sat cannot pay outputs of {} sat and a fee of {} sat",
+            .shortfall.to_sat(), .total_in.to_sat(), .total_out.to_sat(), .fee.to_sat())]
+    InsufficientFunds {
+        shortfall: Amount,
+        total_in: Amount,
+        total_out: Amount,
+        fee: Amount,
+    },
 }
\ No newline at end of file
@@ -0,0 +1,263 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use bitcoin::{OutPoint, Txid};
+use serde::{Deserialize, Serialize};
+use crate::errors::OfflineTransactionError::{FileOperationError, FrozenUtxo as FrozenUtxoError, ParseError};
+use crate::errors::OfflineTransactionResult;
+use crate::types::bitcoin::BitcoinTransactionData;
+use crate::utils::read_file::read_file;
+
+/// Registry of the UTXOs which must never be spent
+///
+/// UTXOs under legal hold, received in a dust attack or reserved for a pending payout are
+/// frozen in a local JSON file. Requests spending a frozen UTXO as an input are rejected, and
+/// frozen UTXOs of the pool are left out of coin selection and consolidation: the pool lists
+/// every UTXO of the wallet, frozen or not, and only offers them to coin selection, so a frozen
+/// pool UTXO is never spent and rejecting the request would block the whole wallet.
+///
+/// # Fields
+///
+/// * `utxos` - The frozen UTXOs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrozenRegistry {
+    #[serde(default)]
+    utxos: Vec<FrozenUtxo>,
+}
+
+/// A frozen UTXO
+///
+/// # Fields
+///
+/// * `txid` - Transaction ID of the UTXO
+/// * `vout` - Output index of the UTXO
+/// * `reason` - Why the UTXO is frozen
+/// * `frozen_at` - UNIX timestamp at which the UTXO was frozen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrozenUtxo {
+    txid: String,
+    vout: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(default)]
+    frozen_at: u64,
+}
+
+impl FrozenUtxo {
+    pub fn txid(&self) -> &str {
+        &self.txid
+    }
+
+    pub fn vout(&self) -> u32 {
+        self.vout
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    pub fn frozen_at(&self) -> u64 {
+        self.frozen_at
+    }
+
+    fn matches(&self, outpoint: &OutPoint) -> bool {
+        self.vout == outpoint.vout && Txid::from_str(&self.txid).is_ok_and(|txid| txid == outpoint.txid)
+    }
+}
+
+impl FrozenRegistry {
+    /// Creates an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the registry from a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the registry file
+    ///
+    /// # Returns
+    ///
+    /// * `OfflineTransactionResult<Self>` - The registry, or an error if the file is not found
+    ///   or cannot be parsed
+    pub fn load<P: AsRef<Path>>(path: P) -> OfflineTransactionResult<Self> {
+        let contents = read_file(path.as_ref())?;
+
+        serde_json::from_str(&contents)
+            .map_err(|e| ParseError {
+                from: "Json".to_string(),
+                to: "FrozenRegistry".to_string(),
+                reason: e.to_string(),
+            })
+    }
+
+    /// Loads the registry from a file, or creates an empty one if the file does not exist yet
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the registry file
+    pub fn load_or_new<P: AsRef<Path>>(path: P) -> OfflineTransactionResult<Self> {
+        if path.as_ref().exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::new())
+        }
+    }
+
+    /// Writes the registry to a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the registry file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> OfflineTransactionResult<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ParseError {
+                from: "FrozenRegistry".to_string(),
+                to: "Json".to_string(),
+                reason: e.to_string(),
+            })?;
+
+        fs::write(path, json)
+            .map_err(|e| FileOperationError {
+                operation: "write".to_string(),
+                reason: e.to_string(),
+            })
+    }
+
+    /// Freezes a UTXO
+    ///
+    /// # Arguments
+    ///
+    /// * `txid` - Transaction ID of the UTXO
+    /// * `vout` - Output index of the UTXO
+    /// * `reason` - Why the UTXO is frozen
+    ///
+    /// # Returns
+    ///
+    /// * `OfflineTransactionResult<bool>` - Whether the UTXO was newly frozen, or an error if
+    ///   the transaction ID cannot be parsed
+    pub fn freeze(&mut self, txid: &str, vout: u32, reason: Option<&str>) -> OfflineTransactionResult<bool> {
+        let txid = parse_txid(txid)?;
+        if self.find(&OutPoint::new(txid, vout)).is_some() {
+            return Ok(false);
+        }
+
+        self.utxos.push(FrozenUtxo {
+            txid: txid.to_string(),
+            vout,
+            reason: reason.map(str::to_string),
+            frozen_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+        });
+
+        Ok(true)
+    }
+
+    /// Unfreezes a UTXO
+    ///
+    /// # Arguments
+    ///
+    /// * `txid` - Transaction ID of the UTXO
+    /// * `vout` - Output index of the UTXO
+    ///
+    /// # Returns
+    ///
+    /// * `OfflineTransactionResult<bool>` - Whether the UTXO was frozen, or an error if the
+    ///   transaction ID cannot be parsed
+    pub fn unfreeze(&mut self, txid: &str, vout: u32) -> OfflineTransactionResult<bool> {
+        let outpoint = OutPoint::new(parse_txid(txid)?, vout);
+        let count = self.utxos.len();
+        self.utxos.retain(|utxo| !utxo.matches(&outpoint));
+
+        Ok(self.utxos.len() < count)
+    }
+
+    /// Returns the frozen UTXOs in the order they were frozen
+    pub fn utxos(&self) -> &[FrozenUtxo] {
+        &self.utxos
+    }
+
+    /// Finds the frozen UTXO of an outpoint
+    pub fn find(&self, outpoint: &OutPoint) -> Option<&FrozenUtxo> {
+        self.utxos.iter().find(|utxo| utxo.matches(outpoint))
+    }
+
+    /// Loads the registry specified by `frozenUtxos`
+    ///
+    /// The registry fails closed: a registry which is specified but cannot be read is an error.
+    /// Without `frozenUtxos` the caller keeps no registry and no UTXO is frozen; the CLI always
+    /// specifies its `--registry`.
+    pub(crate) fn for_transaction(params: &BitcoinTransactionData) -> OfflineTransactionResult<Self> {
+        params.frozen_utxos().map_or_else(|| Ok(Self::new()), Self::load)
+    }
+
+    /// Whether the UTXO is frozen, an unparsable transaction ID is never frozen
+    pub(crate) fn is_frozen(&self, txid: &str, vout: u32) -> bool {
+        Txid::from_str(txid).is_ok_and(|txid| self.find(&OutPoint::new(txid, vout)).is_some())
+    }
+
+    /// Rejects spending a frozen outpoint
+    pub(crate) fn check(&self, outpoint: &OutPoint) -> OfflineTransactionResult<()> {
+        match self.find(outpoint) {
+            Some(utxo) => Err(FrozenUtxoError {
+                outpoint: outpoint.to_string(),
+                reason: utxo.reason().unwrap_or("no reason recorded").to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_txid(txid: &str) -> OfflineTransactionResult<Txid> {
+    Txid::from_str(txid)
+        .map_err(|e| ParseError {
+            from: "string".to_string(),
+            to: "Txid".to_string(),
+            reason: e.to_string()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+
+    #[test]
+    fn test_freeze_and_unfreeze() {
+        let mut registry = FrozenRegistry::new();
+        assert!(registry.freeze(TXID, 1, Some("legal hold")).unwrap());
+        assert!(!registry.freeze(TXID, 1, None).unwrap());
+        assert!(registry.is_frozen(TXID, 1));
+        assert!(!registry.is_frozen(TXID, 0));
+
+        let outpoint = OutPoint::new(Txid::from_str(TXID).unwrap(), 1);
+        let error = registry.check(&outpoint).unwrap_err();
+        assert!(error.to_string().contains("legal hold"));
+
+        assert!(registry.unfreeze(TXID, 1).unwrap());
+        assert!(!registry.unfreeze(TXID, 1).unwrap());
+        assert!(registry.check(&outpoint).is_ok());
+        assert!(registry.freeze("not a txid", 0, None).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frozen.json");
+        assert!(FrozenRegistry::load(&path).is_err());
+
+        let mut registry = FrozenRegistry::load_or_new(&path).unwrap();
+        registry.freeze(TXID, 0, Some("dust attack")).unwrap();
+        registry.save(&path).unwrap();
+
+        let loaded = FrozenRegistry::load(&path).unwrap();
+        assert_eq!(loaded, registry);
+        assert_eq!(loaded.utxos()[0].reason(), Some("dust attack"));
+    }
+}
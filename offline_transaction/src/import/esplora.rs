@@ -0,0 +1,141 @@
+use bitcoin::Amount;
+use serde::Deserialize;
+use crate::errors::OfflineTransactionError::ParseError;
+use crate::errors::OfflineTransactionResult;
+use crate::import::{ImportTarget, UtxoFilter};
+use crate::types::bitcoin::{BitcoinTransactionData, BitcoinTransactionInput};
+use crate::utils::str_to_address::str_to_address;
+
+/// An entry of the Esplora `/address/:address/utxo` response
+#[derive(Debug, Deserialize)]
+struct EsploraUtxo {
+    txid: String,
+    vout: u32,
+    value: u64,
+    status: EsploraStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct EsploraStatus {
+    confirmed: bool,
+    #[serde(default)]
+    block_height: Option<u32>,
+}
+
+impl BitcoinTransactionData {
+    /// Imports the UTXOs of an address returned by Esplora or mempool.space
+    ///
+    /// The response of `/address/:address/utxo` does not include the address, so it has to be
+    /// given. The confirmations are counted from `tip_height` (`/blocks/tip/height`) if given,
+    /// otherwise confirmed UTXOs are counted as 1 confirmation and unconfirmed ones as 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - The saved `/address/:address/utxo` response
+    /// * `address` - The address the UTXOs belong to
+    /// * `tip_height` - Height of the chain tip when the response was saved
+    /// * `filter` - Filter of the imported UTXOs
+    /// * `target` - Whether the UTXOs are added to the inputs or the UTXO pool
+    ///
+    /// # Returns
+    ///
+    /// * `OfflineTransactionResult<usize>` - Number of imported UTXOs, or an error if:
+    ///   - The JSON is not an Esplora UTXO response
+    ///   - The address cannot be parsed or belongs to another network
+    pub fn import_esplora(&mut self,
+                          json: &str,
+                          address: &str,
+                          tip_height: Option<u32>,
+                          filter: &UtxoFilter,
+                          target: ImportTarget) -> OfflineTransactionResult<usize> {
+        let utxos: Vec<EsploraUtxo> = serde_json::from_str(json)
+            .map_err(|e| ParseError {
+                from: "Json".to_string(),
+                to: "EsploraUtxo".to_string(),
+                reason: e.to_string()
+            })?;
+        str_to_address(address, self.network(), "address")?;
+
+        let imported = utxos.into_iter()
+            .filter_map(|utxo| {
+                let confirmations = utxo.status.confirmations(tip_height);
+                filter.accepts(address, confirmations).then(|| BitcoinTransactionInput::new(
+                    &utxo.txid,
+                    utxo.vout,
+                    Amount::from_sat(utxo.value),
+                    address,
+                    Some(confirmations),
+                    None))
+            })
+            .collect::<Vec<_>>();
+
+        let count = imported.len();
+        for input in imported {
+            self.push_imported(target, input);
+        }
+
+        Ok(count)
+    }
+}
+
+impl EsploraStatus {
+    fn confirmations(&self, tip_height: Option<u32>) -> u32 {
+        match (self.confirmed, self.block_height, tip_height) {
+            (false, _, _) => 0,
+            (true, Some(block_height), Some(tip_height)) => tip_height.saturating_sub(block_height) + 1,
+            (true, _, _) => 1,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::amount::BitcoinAmount;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    const ESPLORA_UTXOS: &str = r#"[
+        {
+            "txid": "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+            "vout": 0,
+            "status": {
+                "confirmed": true,
+                "block_height": 840000,
+                "block_hash": "0000000000000000000320283a032748cef8227873ff4872689bf23f1cda83a5",
+                "block_time": 1713571767
+            },
+            "value": 1000000
+        },
+        {
+            "txid": "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+            "vout": 1,
+            "status": {"confirmed": false},
+            "value": 29000000
+        }
+    ]"#;
+
+    #[test]
+    fn test_import_esplora() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, "key");
+        let count = data.import_esplora(
+            ESPLORA_UTXOS, ADDRESS, Some(840_005), &UtxoFilter::new(), ImportTarget::Utxos).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(data.utxos()[0].amount(), &BitcoinAmount::Exact(Amount::from_sat(1_000_000)));
+        assert_eq!(data.utxos()[0].confirmations(), Some(6));
+        assert_eq!(data.utxos()[1].address(), ADDRESS);
+        assert_eq!(data.utxos()[1].confirmations(), Some(0));
+    }
+
+    #[test]
+    fn test_import_esplora_min_confirmations() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, "key");
+        let mut filter = UtxoFilter::new();
+        filter.set_min_confirmations(1);
+        let count = data.import_esplora(ESPLORA_UTXOS, ADDRESS, None, &filter, ImportTarget::Inputs).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(data.inputs()[0].confirmations(), Some(1));
+
+        assert!(data.import_esplora(ESPLORA_UTXOS, "address", None, &filter, ImportTarget::Inputs).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use bitcoin::{Address, Amount, Network, ScriptBuf};
+use serde::Deserialize;
+use crate::errors::OfflineTransactionError::{InvalidTransaction, ParseError};
+use crate::errors::OfflineTransactionResult;
+use crate::import::{ImportTarget, UtxoFilter};
+use crate::types::bitcoin::{BitcoinTransactionData, BitcoinTransactionInput};
+use crate::utils::btc_sat_trans::deserialize_btc_number;
+use crate::utils::str_to_address::str_to_address;
+
+/// An entry of the `bitcoin-cli listunspent` result
+///
+/// Only the fields used to build the inputs are read. `address` is missing for
+/// outputs without a standard address, in which case it is derived from `scriptPubKey`.
+/// A non-empty `label` becomes the cluster of the UTXO.
+#[derive(Debug, Deserialize)]
+struct ListUnspentEntry {
+    txid: String,
+    vout: u32,
+    #[serde(deserialize_with = "deserialize_btc_number")]
+    amount: Amount,
+    address: Option<String>,
+    #[serde(rename = "scriptPubKey")]
+    script_pubkey: String,
+    #[serde(default)]
+    confirmations: u32,
+    #[serde(default)]
+    label: String,
+}
+
+impl BitcoinTransactionData {
+    /// Imports the UTXOs listed by `bitcoin-cli listunspent`
+    ///
+    /// Every UTXO passing the filter is added to the inputs or the UTXO pool with its
+    /// address, confirmations and label as cluster. The address is checked against the `scriptPubKey`.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - The JSON array printed by `bitcoin-cli listunspent`
+    /// * `filter` - Filter of the imported UTXOs
+    /// * `target` - Whether the UTXOs are added to the inputs or the UTXO pool
+    ///
+    /// # Returns
+    ///
+    /// * `OfflineTransactionResult<usize>` - Number of imported UTXOs, or an error if:
+    ///   - The JSON is not a `listunspent` result
+    ///   - An address belongs to another network or does not match its `scriptPubKey`, or cannot be derived from it
+    pub fn import_listunspent(&mut self,
+                              json: &str,
+                              filter: &UtxoFilter,
+                              target: ImportTarget) -> OfflineTransactionResult<usize> {
+        let entries: Vec<ListUnspentEntry> = serde_json::from_str(json)
+            .map_err(|e| ParseError {
+                from: "Json".to_string(),
+                to: "listunspent".to_string(),
+                reason: e.to_string()
+            })?;
+
+        let mut imported = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            let address = entry_address(entry, index, self.network())?;
+            if filter.accepts(&address, entry.confirmations) {
+                let cluster = Some(entry.label.clone()).filter(|label| !label.is_empty());
+                imported.push(BitcoinTransactionInput::new(
+                    &entry.txid, entry.vout, entry.amount, &address, Some(entry.confirmations), cluster));
+            }
+        }
+
+        let count = imported.len();
+        for input in imported {
+            self.push_imported(target, input);
+        }
+
+        Ok(count)
+    }
+}
+
+/// Resolves the address of an entry, checking it against the network and the scriptPubKey
+fn entry_address(entry: &ListUnspentEntry, index: usize, network: Network) -> OfflineTransactionResult<String> {
+    let script_pubkey = ScriptBuf::from_hex(&entry.script_pubkey)
+        .map_err(|e| ParseError {
+            from: "hex".to_string(),
+            to: "ScriptBuf".to_string(),
+            reason: e.to_string()
+        })?;
+
+    match &entry.address {
+        Some(address) => {
+            if str_to_address(address, network, &format!("[{}].address", index))?.script_pubkey() != script_pubkey {
+                return Err(InvalidTransaction {
+                    reason: format!("address {} of {}:{} does not match its scriptPubKey {}",
+                                    address, entry.txid, entry.vout, entry.script_pubkey),
+                });
+            }
+            Ok(address.clone())
+        },
+        None => {
+            Address::from_script(&script_pubkey, network)
+                .map(|address| address.to_string())
+                .map_err(|e| ParseError {
+                    from: "scriptPubKey".to_string(),
+                    to: "Address".to_string(),
+                    reason: format!("{}:{} {}", entry.txid, entry.vout, e)
+                })
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTUNSPENT: &str = r#"[
+        {
+            "txid": "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+            "vout": 0,
+            "address": "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            "label": "customer-1",
+            "scriptPubKey": "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+            "amount": 0.01000000,
+            "confirmations": 12,
+            "spendable": true,
+            "solvable": true,
+            "safe": true
+        },
+        {
+            "txid": "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+            "vout": 1,
+            "scriptPubKey": "76a914751e76e8199196d454941c45d1b3a323f1433bd688ac",
+            "amount": 0.00200000,
+            "confirmations": 0,
+            "spendable": true,
+            "solvable": true,
+            "safe": false
+        }
+    ]"#;
+
+    #[test]
+    fn test_import_listunspent() {
+        let mut data = BitcoinTransactionData::new("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", "key");
+        let count = data.import_listunspent(LISTUNSPENT, &UtxoFilter::new(), ImportTarget::Utxos).unwrap();
+        assert_eq!(count, 2);
+        assert!(data.inputs().is_empty());
+        assert_eq!(data.utxos()[0].confirmations(), Some(12));
+        assert_eq!(data.utxos()[0].cluster(), Some("customer-1"));
+        assert_eq!(data.utxos()[1].cluster(), None);
+        // The address is derived from the scriptPubKey
+        assert_eq!(data.utxos()[1].address(), "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+    }
+
+    #[test]
+    fn test_import_listunspent_filters() {
+        let mut data = BitcoinTransactionData::new("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", "key");
+        let mut filter = UtxoFilter::new();
+        filter.set_min_confirmations(1);
+        assert_eq!(data.import_listunspent(LISTUNSPENT, &filter, ImportTarget::Inputs).unwrap(), 1);
+        assert_eq!(data.inputs()[0].vout(), 0);
+
+        let mut filter = UtxoFilter::new();
+        filter.push_address("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+        assert_eq!(data.import_listunspent(LISTUNSPENT, &filter, ImportTarget::Inputs).unwrap(), 1);
+        assert_eq!(data.inputs()[1].vout(), 1);
+    }
+
+    #[test]
+    fn test_import_listunspent_mismatched_address() {
+        let json = LISTUNSPENT.replace("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa");
+        let mut data = BitcoinTransactionData::new("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", "key");
+        assert!(matches!(
+            data.import_listunspent(&json, &UtxoFilter::new(), ImportTarget::Utxos),
+            Err(InvalidTransaction { .. })));
+        assert!(data.utxos().is_empty());
+    }
+}
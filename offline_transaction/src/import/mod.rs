@@ -0,0 +1,84 @@
+//! Importers converting UTXO listings of online wallets and explorers into transaction data
+//!
+//! The listings are saved in the online environment and copied to the offline one, so that
+//! the inputs or the UTXO pool of a request do not have to be retyped.
+
+use serde::{Deserialize, Serialize};
+
+pub mod esplora;
+pub mod listunspent;
+
+
+/// Where the imported UTXOs are added to the transaction data
+///
+/// # Variants
+///
+/// * `Inputs` - Spend every imported UTXO as an input
+/// * `Utxos` - Add the imported UTXOs to the pool used for automatic coin selection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportTarget {
+    Inputs,
+    #[default]
+    Utxos,
+}
+
+/// Filters the UTXOs of an import
+///
+/// # Fields
+///
+/// * `min_confirmations` - UTXOs with fewer confirmations are skipped
+/// * `addresses` - If not empty, UTXOs of other addresses are skipped
+#[derive(Debug, Clone, Default)]
+pub struct UtxoFilter {
+    min_confirmations: u32,
+    addresses: Vec<String>,
+}
+
+impl UtxoFilter {
+    /// Creates a filter accepting every UTXO
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the minimum number of confirmations of the imported UTXOs
+    ///
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_confirmations` - The minimum number of confirmations
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_min_confirmations(&mut self, min_confirmations: u32) -> &mut Self {
+        self.min_confirmations = min_confirmations;
+
+        self
+    }
+
+    /// Adds an address whose UTXOs are imported
+    ///
+    /// Once an address is added, only the UTXOs of the added addresses are imported.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn push_address(&mut self, address: &str) -> &mut Self {
+        self.addresses.push(address.to_string());
+
+        self
+    }
+
+    /// Returns whether a UTXO of the address with the confirmations passes the filter
+    pub(crate) fn accepts(&self, address: &str, confirmations: u32) -> bool {
+        confirmations >= self.min_confirmations
+            && (self.addresses.is_empty() || self.addresses.iter().any(|accepted| accepted == address))
+    }
+}
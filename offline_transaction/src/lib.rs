@@ -1,15 +1,19 @@
-use std::fs::File;
-use std::io::Read;
 use std::path::Path;
-use crate::errors::OfflineTransactionError::{FileNotFound, FileOperationError, ParseError, UnsupportedError};
+use crate::errors::OfflineTransactionError::{ParseError, UnsupportedError};
 use crate::errors::OfflineTransactionResult;
 use crate::types::bitcoin::BitcoinTransactionData;
-use crate::types::Network;
+use crate::types::{Network, BITCOIN_NETWORKS};
 
+pub mod coin_selection;
 pub mod errors;
+pub mod frozen;
+pub mod import;
 pub mod types;
 pub mod transaction;
 mod utils;
+pub mod validate;
+
+pub use crate::utils::read_file::read_file;
 
 /// An enum representing different blockchain platforms for offline transaction creation.
 /// Currently, this implementation only supports Bitcoin transactions, but it's designed
@@ -44,28 +48,10 @@ impl Platform {
     ///   - File is not found
     ///   - File cannot be read
     ///   - JSON parsing fails
-    ///   - Network type is unsupported (currently only "bitcoin" is supported)
+    ///   - Network type is unsupported (currently "bitcoin", "testnet", "testnet4", "signet" and "regtest" are supported)
     pub fn from_json<JP: AsRef<Path>>(json_path: JP) -> OfflineTransactionResult<Self> {
-        let json_path = json_path.as_ref();
-        if !(json_path.exists() && json_path.is_file()) {
-            return Err(FileNotFound {
-                path: format!("{}", json_path.display())
-            });
-        }
-        
-        let mut file = File::open(json_path)
-            .map_err(|e| FileOperationError {
-                operation: "open".to_string(),
-                reason: e.to_string(),
-            })?;
-        
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)
-            .map_err(|e| FileOperationError {
-                operation: "read".to_string(),
-                reason: e.to_string(),
-            })?;
-        
+        let contents = read_file(json_path.as_ref())?;
+
         Self::json_parser(&contents)
     }
     
@@ -78,7 +64,7 @@ impl Platform {
             })?;
 
         match network_config.network.to_lowercase().trim() {
-            "bitcoin" => {
+            network if BITCOIN_NETWORKS.contains(&network) => {
                 let transaction_data: BitcoinTransactionData = serde_json::from_str(json_str)
                     .map_err(|e| ParseError {
                         from: "Json".to_string(),
@@ -92,11 +78,31 @@ impl Platform {
                 Err(UnsupportedError {
                     component: "network".to_string(),
                     input: network_config.network,
-                    expected: "bitcoin".to_string(),
+                    expected: BITCOIN_NETWORKS.join(", "),
                 })
             }
         }
     }
+
+    /// Serializes the platform to the input JSON format
+    ///
+    /// The JSON can be read back with [`Platform::from_json`], for example after
+    /// UTXOs were imported into the transaction data.
+    ///
+    /// # Returns
+    ///
+    /// * `OfflineTransactionResult<String>` - The pretty printed JSON including the "network" key
+    pub fn to_json(&self) -> OfflineTransactionResult<String> {
+        let json = match self {
+            Platform::Bitcoin(data) => serde_json::to_string_pretty(data),
+        };
+
+        json.map_err(|e| ParseError {
+            from: "Platform".to_string(),
+            to: "Json".to_string(),
+            reason: e.to_string()
+        })
+    }
 }
 
 #[cfg(test)]
@@ -124,6 +130,47 @@ mod tests {
         assert!(result.is_ok());
     }
     
+    #[test]
+    fn test_to_json_round_trip() {
+        let json_str = r#"{"network": "bitcoin", "inputs": [], "outputs": [], "changeAddress": "", "privateKey": "", "feeRate": 3}"#;
+        let json = Platform::json_parser(json_str).unwrap().to_json().unwrap();
+        match Platform::json_parser(&json).unwrap() {
+            Platform::Bitcoin(data) => assert_eq!(data.fee_rate(), Some(3)),
+        }
+    }
+
+    #[test]
+    fn test_json_parser_networks() {
+        for network in ["bitcoin", "testnet", "Testnet4", "signet", " regtest "] {
+            let json_str = format!(
+                r#"{{"network": "{}", "inputs": [], "outputs": [], "changeAddress": "", "privateKey": "", "feeRate": 1}}"#,
+                network);
+            let json = Platform::json_parser(&json_str).unwrap().to_json().unwrap();
+            match Platform::json_parser(&json).unwrap() {
+                Platform::Bitcoin(data) => assert_eq!(data.network().to_string(), network.trim().to_lowercase()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_parser_amounts() {
+        let json = |amount: &str| format!(
+            r#"{{"network": "bitcoin", "inputs": [], "outputs": [{{"address": "", "amount": {}}}], "changeAddress": "", "privateKey": "", "feeRate": 1}}"#,
+            amount);
+
+        let resolve = |amount: &str| match Platform::json_parser(&json(amount)).unwrap() {
+            Platform::Bitcoin(data) => data.outputs()[0].amount().resolve("outputs[0].amount", data.amount_unit()),
+        };
+
+        for (amount, sat) in [(r#""0.29""#, 29_000_000), ("0.29", 29_000_000), (r#""1""#, 100_000_000),
+                              (r#""1.5 mBTC""#, 150_000), (r#""250000 sat""#, 250_000)] {
+            assert_eq!(resolve(amount).unwrap().to_sat(), sat);
+        }
+        for amount in [r#""0.000000001""#, r#""-0.1""#, r#""NaN""#, "-0.1", "0.000000001", r#""1 XBT""#] {
+            assert!(resolve(amount).is_err(), "{} is accepted", amount);
+        }
+    }
+
     #[test]
     fn test_json_parser_error() {
         let json_str = r#"{"network": "ethereum", "inputs": [], "outputs": [], "changeAddress": "", "privateKey": "", "feeRate": 1}"#;
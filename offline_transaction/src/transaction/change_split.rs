@@ -0,0 +1,169 @@
+use bitcoin::{Amount, Denomination};
+use serde::{Deserialize, Serialize};
+use crate::errors::OfflineTransactionError::InvalidTransaction;
+use crate::errors::OfflineTransactionResult;
+use crate::types::amount::BitcoinAmount;
+
+/// Splits the change of a transaction into several outputs
+///
+/// Either `count` or `target_value` has to be specified. Fewer outputs are created if the change
+/// cannot fund every output with at least `min_amount` after paying for the extra outputs.
+///
+/// # Fields
+///
+/// * `count` - Number of change outputs sharing the change equally
+/// * `target_value` - Amount of each change output, the remainder goes to the last one
+/// * `min_amount` - Minimum amount of each change output (default: the dust limit of the change script)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeSplit {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target_value: Option<BitcoinAmount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_amount: Option<BitcoinAmount>,
+}
+
+impl ChangeSplit {
+    /// Creates a split of the change into `count` outputs of equal amount
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The number of change outputs
+    pub fn count(count: u32) -> Self {
+        Self { count: Some(count), ..Self::default() }
+    }
+
+    /// Creates a split of the change into outputs of `target_value`
+    ///
+    /// # Arguments
+    ///
+    /// * `target_value` - The amount of each change output
+    pub fn target_value(target_value: impl Into<BitcoinAmount>) -> Self {
+        Self { target_value: Some(target_value.into()), ..Self::default() }
+    }
+
+    /// Updates the minimum amount of each change output
+    ///
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_amount` - The minimum amount
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_min_amount(&mut self, min_amount: impl Into<BitcoinAmount>) -> &mut Self {
+        self.min_amount = Some(min_amount.into());
+
+        self
+    }
+
+    /// Returns the paths of the amounts given as JSON numbers, which are deprecated
+    pub(crate) fn number_amounts(&self) -> Vec<String> {
+        [("changeSplit.targetValue", &self.target_value), ("changeSplit.minAmount", &self.min_amount)]
+            .into_iter()
+            .filter(|(_, amount)| amount.as_ref().is_some_and(BitcoinAmount::is_number))
+            .map(|(path, _)| path.to_string())
+            .collect()
+    }
+
+    /// Computes the amounts of the change outputs
+    ///
+    /// Every change output pays `change_fee` for its own size, so the number of outputs is
+    /// lowered until each of them gets at least the minimum amount. A single output receives
+    /// the whole change as without splitting.
+    ///
+    /// # Arguments
+    ///
+    /// * `excess` - Amount left for the change outputs and their fee (satoshi)
+    /// * `change_fee` - Fee of one change output (satoshi)
+    /// * `dust_limit` - Dust limit of the change script (satoshi)
+    /// * `amount_unit` - Unit of the amounts given without a unit
+    ///
+    /// # Returns
+    ///
+    /// * `OfflineTransactionResult<Vec<u64>>` - The amounts of the change outputs, or an error if the
+    ///   split is not specified by exactly one positive `count` or `target_value`, or an amount is invalid
+    pub(crate) fn amounts(&self,
+                          excess: u64,
+                          change_fee: u64,
+                          dust_limit: u64,
+                          amount_unit: Denomination) -> OfflineTransactionResult<Vec<u64>> {
+        let minimum = self.min_amount.as_ref()
+            .map(|min_amount| min_amount.resolve("changeSplit.minAmount", amount_unit))
+            .transpose()?
+            .map(Amount::to_sat)
+            .unwrap_or_default()
+            .max(dust_limit);
+        let target_value = self.target_value.as_ref()
+            .map(|target_value| target_value.resolve("changeSplit.targetValue", amount_unit))
+            .transpose()?;
+        // Largest number of outputs which all get the minimum amount
+        let max_count = (excess / (minimum + change_fee).max(1)).max(1);
+
+        let (count, target) = match (self.count, target_value) {
+            (Some(count), None) if count > 0 => (u64::from(count), None),
+            (None, Some(target_value)) if target_value > Amount::ZERO => {
+                let target = target_value.to_sat();
+                ((excess / (target + change_fee)).max(1), Some(target))
+            },
+            _ => return Err(InvalidTransaction {
+                reason: "changeSplit needs either a positive count or a positive targetValue".to_string(),
+            }),
+        };
+        let count = count.min(max_count);
+        let total = excess.saturating_sub(count * change_fee);
+
+        let mut amounts = match target {
+            // `count` never exceeds the outputs of `target` the change can fund
+            Some(target) if count * target <= total => vec![target; count as usize],
+            _ => vec![total / count; count as usize],
+        };
+        let remainder = total - amounts.iter().sum::<u64>();
+        if let Some(last) = amounts.last_mut() {
+            *last += remainder;
+        }
+
+        Ok(amounts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_by_count() {
+        let amounts = ChangeSplit::count(3).amounts(1_000_100, 31, 294, Denomination::Bitcoin).unwrap();
+        assert_eq!(amounts, vec![333_335, 333_335, 333_337]);
+        assert_eq!(amounts.iter().sum::<u64>() + 3 * 31, 1_000_100);
+    }
+
+    #[test]
+    fn test_split_by_target_value() {
+        let amounts = ChangeSplit::target_value("3 mBTC").amounts(1_000_000, 31, 294, Denomination::Bitcoin).unwrap();
+        assert_eq!(amounts, vec![300_000, 300_000, 399_907]);
+    }
+
+    #[test]
+    fn test_split_respects_min_amount() {
+        let amounts = ChangeSplit::count(10)
+            .set_min_amount(Amount::from_sat(300_000))
+            .amounts(1_000_000, 31, 294, Denomination::Bitcoin)
+            .unwrap();
+        assert_eq!(amounts.len(), 3);
+        assert!(amounts.iter().all(|amount| *amount >= 300_000));
+
+        // The change is kept in one output when it cannot be split
+        assert_eq!(ChangeSplit::count(2).amounts(500, 31, 294, Denomination::Bitcoin).unwrap(), vec![469]);
+    }
+
+    #[test]
+    fn test_split_needs_count_or_target_value() {
+        assert!(ChangeSplit::count(0).amounts(1_000_000, 31, 294, Denomination::Bitcoin).is_err());
+        assert!(ChangeSplit::default().amounts(1_000_000, 31, 294, Denomination::Bitcoin).is_err());
+    }
+}
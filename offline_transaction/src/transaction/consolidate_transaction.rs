@@ -0,0 +1,324 @@
+use std::str::FromStr;
+use bitcoin::{Amount, OutPoint, ScriptBuf, Transaction, TxIn, TxOut, Txid, Witness};
+use bitcoin::absolute::LockTime;
+use bitcoin::transaction::Version;
+use crate::errors::OfflineTransactionError::{InvalidTransaction, MissingParameter, ParseError};
+use crate::errors::OfflineTransactionResult;
+use crate::frozen::FrozenRegistry;
+use crate::Platform;
+use crate::transaction::generate_transaction::input_sequence;
+use crate::transaction::report::{ConsolidationReport, GenerationReport, SelectedUtxo};
+use crate::transaction::TransactionData;
+use crate::types::bitcoin::BitcoinTransactionInput;
+use crate::types::amount::checked_sum;
+use crate::utils::fee_calculator::{dust_limit, estimate_weight, fee_calculator, input_vsize, MAX_STANDARD_TX_WEIGHT};
+use crate::utils::str_to_address::str_to_address;
+
+/// A UTXO to consolidate with its amount in satoshis and script_pubkey
+type ConsolidatedUtxo<'a> = (&'a BitcoinTransactionInput, u64, ScriptBuf);
+
+impl Platform {
+    /// Generates unsigned transactions consolidating the UTXO pool into a single address
+    ///
+    /// Every UTXO of `utxos` worth more than the fee to spend it at the fee rate is consolidated
+    /// to `consolidationAddress` (or the change address). The UTXOs are split into as many
+    /// transactions as needed to keep each one under `maxTxWeight`, which is capped by the
+    /// standardness limit of 100 kvB:
+    /// 1. Leaves out the frozen UTXOs and the UTXOs which are uneconomical at the fee rate
+    /// 2. Fills each transaction with UTXOs in pool order up to the maximum weight
+    /// 3. Pays the amount of each transaction minus its fee to the consolidation address
+    ///
+    /// # Returns
+    ///
+    /// Returns a Result containing either:
+    /// * `Vec<TransactionData>` - The unsigned consolidation transactions
+    /// * `OfflineTransactionError` - If the UTXOs cannot be consolidated
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * `utxos` is empty
+    /// * No UTXO is economical at the fee rate
+    /// * The maximum weight does not fit a single input
+    /// * A transaction cannot pay its fee without leaving a dust output
+    /// * An outpoint is listed twice in `utxos`
+    pub fn generate_consolidation_transactions(&self) -> OfflineTransactionResult<Vec<TransactionData>> {
+        match self {
+            Platform::Bitcoin(params) => {
+                let report = GenerationReport::for_transaction(params)?;
+                if params.utxos().is_empty() {
+                    return Err(MissingParameter {
+                        parameter: "utxos".to_string(),
+                        operation: "consolidate UTXOs".to_string(),
+                    });
+                }
+
+                let (address, field) = match params.consolidation_address() {
+                    Some(address) => (address, "consolidationAddress"),
+                    None => (params.change_address(), "changeAddress"),
+                };
+                let output = TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: str_to_address(address, params.network(), field)?.script_pubkey(),
+                };
+                let max_weight = params.max_tx_weight()
+                    .map_or(MAX_STANDARD_TX_WEIGHT, |weight| weight.min(MAX_STANDARD_TX_WEIGHT));
+
+                let frozen = FrozenRegistry::for_transaction(params)?;
+                let mut economical = Vec::new();
+                let mut uneconomical = Vec::new();
+                let mut frozen_utxos = Vec::new();
+                let mut outpoints: Vec<OutPoint> = Vec::new();
+                for (index, utxo) in params.utxos().iter().enumerate() {
+                    let txid = Txid::from_str(utxo.txid())
+                        .map_err(|e| ParseError {
+                            from: "string".to_string(),
+                            to: "Txid".to_string(),
+                            reason: e.to_string()
+                        })?;
+                    let outpoint = OutPoint::new(txid, utxo.vout());
+                    // Spending an output twice makes the transaction invalid by consensus
+                    if let Some(first) = outpoints.iter().position(|other| *other == outpoint) {
+                        return Err(InvalidTransaction {
+                            reason: format!("utxos[{}] spends {} as utxos[{}] does", index, outpoint, first),
+                        });
+                    }
+                    outpoints.push(outpoint);
+
+                    if frozen.is_frozen(utxo.txid(), utxo.vout()) {
+                        frozen_utxos.push(format!("{}:{}", utxo.txid(), utxo.vout()));
+                        continue;
+                    }
+
+                    let script_pubkey = str_to_address(
+                        utxo.address(), params.network(), &format!("utxos[{}].address", index))?.script_pubkey();
+                    let amount = utxo.amount()
+                        .resolve(&format!("utxos[{}].amount", index), params.amount_unit())?
+                        .to_sat();
+                    let fee = input_vsize(&script_pubkey)
+                        .checked_mul(report.fee_rate)
+                        .ok_or_else(|| InvalidTransaction {
+                            reason: format!("fee overflows at fee rate {} sat/vB", report.fee_rate),
+                        })?;
+
+                    if amount > fee {
+                        economical.push((utxo, amount, script_pubkey));
+                    } else {
+                        uneconomical.push(SelectedUtxo {
+                            txid: utxo.txid().to_string(),
+                            vout: utxo.vout(),
+                            amount,
+                            effective_value: amount as i64 - fee as i64,
+                        });
+                    }
+                }
+                if economical.is_empty() {
+                    return Err(InvalidTransaction {
+                        reason: format!("no UTXO of the pool is worth spending at {} sat/vB", report.fee_rate),
+                    });
+                }
+
+                let parts = split_by_weight(economical, &output, max_weight)?;
+                let part_count = parts.len();
+
+                parts.into_iter()
+                    .enumerate()
+                    .map(|(index, part)| {
+                        let input_scripts = part.iter()
+                            .map(|(_, _, script_pubkey)| script_pubkey.clone())
+                            .collect::<Vec<_>>();
+                        let total_amount = checked_sum(
+                            part.iter().map(|(_, amount, _)| Amount::from_sat(*amount)), "UTXO amounts")?.to_sat();
+                        let fee = fee_calculator(&input_scripts, std::slice::from_ref(&output), report.fee_rate)?;
+                        let amount = total_amount.checked_sub(fee)
+                            .filter(|amount| *amount >= dust_limit(&output.script_pubkey))
+                            .ok_or_else(|| InvalidTransaction {
+                                reason: format!(
+                                    "consolidation transaction {} of {} sat cannot pay its fee of {} sat",
+                                    index + 1, total_amount, fee),
+                            })?;
+
+                        let sequence = input_sequence(params.rbf(), params.lock_time().is_some());
+                        let tx_inputs = part.iter()
+                            .map(|(utxo, _, _)| {
+                                let txid = Txid::from_str(utxo.txid())
+                                    .map_err(|e| ParseError {
+                                        from: "string".to_string(),
+                                        to: "Txid".to_string(),
+                                        reason: e.to_string()
+                                    })?;
+                                Ok(TxIn {
+                                    previous_output: OutPoint::new(txid, utxo.vout()),
+                                    script_sig: ScriptBuf::new(),
+                                    sequence,
+                                    witness: Witness::new(),
+                                })
+                            })
+                            .collect::<OfflineTransactionResult<Vec<_>>>()?;
+
+                        let tx = Transaction {
+                            version: Version::TWO,
+                            lock_time: params.lock_time()
+                                .map(LockTime::from_consensus)
+                                .unwrap_or(LockTime::ZERO),
+                            input: tx_inputs,
+                            output: vec![TxOut { value: Amount::from_sat(amount), ..output.clone() }],
+                        };
+
+                        let mut report = report.clone();
+                        if index == 0 {
+                            report.frozen_utxos = frozen_utxos.clone();
+                        }
+                        report.consolidation = Some(ConsolidationReport {
+                            part: index + 1,
+                            parts: part_count,
+                            inputs: part.len(),
+                            weight: estimate_weight(&input_scripts, &tx.output),
+                            uneconomical: uneconomical.clone(),
+                        });
+
+                        Ok(TransactionData::BitcoinTransaction {
+                            raw_transaction: tx,
+                            signed: false,
+                            input_data: part.into_iter()
+                                .map(|(_, amount, script_pubkey)| (amount, script_pubkey))
+                                .collect(),
+                            private_key: params.private_key().to_string(),
+                            network: params.network(),
+                            report,
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Splits the UTXOs in pool order into groups whose transaction stays within the maximum weight
+fn split_by_weight<'a>(utxos: Vec<ConsolidatedUtxo<'a>>,
+                       output: &TxOut,
+                       max_weight: u64) -> OfflineTransactionResult<Vec<Vec<ConsolidatedUtxo<'a>>>> {
+    let mut parts = Vec::new();
+    let mut part: Vec<ConsolidatedUtxo> = Vec::new();
+    let mut part_scripts = Vec::new();
+
+    for utxo in utxos {
+        part_scripts.push(utxo.2.clone());
+        if estimate_weight(&part_scripts, std::slice::from_ref(output)) > max_weight {
+            if part.is_empty() {
+                return Err(InvalidTransaction {
+                    reason: format!("a consolidation transaction spending a single UTXO exceeds the \
+                                     maximum weight of {} WU", max_weight),
+                });
+            }
+            parts.push(std::mem::take(&mut part));
+            part_scripts = vec![utxo.2.clone()];
+        }
+        part.push(utxo);
+    }
+    parts.push(part);
+
+    Ok(parts)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Network;
+    use crate::types::bitcoin::BitcoinTransactionData;
+    use crate::utils::fee_calculator::input_weight;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    const DESTINATION: &str = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa";
+    const PRIVATE_KEY: &str = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+    const TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+
+    #[test]
+    fn test_consolidation_skips_uneconomical_utxos() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        // 68 vbytes P2WPKH inputs cost 340 sat at 5 sat/vB
+        data.push_utxo(TXID, 0, Amount::from_sat(10_000))
+            .push_utxo(TXID, 1, Amount::from_sat(340))
+            .push_utxo(TXID, 2, Amount::from_sat(20_000))
+            .set_fee_rate(5)
+            .set_consolidation_address(DESTINATION);
+
+        let transactions = Platform::Bitcoin(data).generate_consolidation_transactions().unwrap();
+        assert_eq!(transactions.len(), 1);
+        let consolidation = transactions[0].report().consolidation.clone().unwrap();
+        assert_eq!((consolidation.part, consolidation.parts, consolidation.inputs), (1, 1, 2));
+        assert_eq!(consolidation.uneconomical[0].vout, 1);
+        match &transactions[0] {
+            TransactionData::BitcoinTransaction { raw_transaction, .. } => {
+                assert_eq!(raw_transaction.output.len(), 1);
+                assert_eq!(raw_transaction.output[0].script_pubkey,
+                           str_to_address(DESTINATION, Network::Bitcoin, "address").unwrap().script_pubkey());
+                // 30000 sat minus the fee of 11 + 2 * 68 + 34 vbytes
+                assert_eq!(raw_transaction.output[0].value.to_sat(), 30_000 - 181 * 5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_consolidation_skips_frozen_utxos() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frozen.json");
+        let mut registry = FrozenRegistry::new();
+        registry.freeze(TXID, 1, None).unwrap();
+        registry.save(&path).unwrap();
+
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_utxo(TXID, 0, Amount::from_sat(10_000))
+            .push_utxo(TXID, 1, Amount::from_sat(20_000))
+            .set_fee_rate(1)
+            .set_frozen_utxos(path.to_str().unwrap());
+
+        let transactions = Platform::Bitcoin(data).generate_consolidation_transactions().unwrap();
+        assert_eq!(transactions[0].report().frozen_utxos, vec![format!("{}:1", TXID)]);
+        assert_eq!(transactions[0].report().consolidation.as_ref().unwrap().inputs, 1);
+    }
+
+    #[test]
+    fn test_consolidation_splits_by_weight() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        for vout in 0..5 {
+            data.push_utxo(TXID, vout, Amount::from_sat(100_000));
+        }
+        // Room for 2 inputs per transaction
+        let script_pubkey = str_to_address(ADDRESS, Network::Bitcoin, "address").unwrap().script_pubkey();
+        data.set_max_tx_weight(200 + 2 * input_weight(&script_pubkey));
+
+        let transactions = Platform::Bitcoin(data).generate_consolidation_transactions().unwrap();
+        let inputs = transactions.iter()
+            .map(|transaction| transaction.report().consolidation.clone().unwrap())
+            .map(|consolidation| {
+                assert!(consolidation.weight <= 200 + 2 * input_weight(&script_pubkey));
+                consolidation.inputs
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(inputs, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn test_consolidation_refuses_duplicate_utxos() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_utxo(TXID, 0, Amount::from_sat(100_000))
+            .push_utxo(TXID, 1, Amount::from_sat(100_000))
+            .push_utxo(TXID, 0, Amount::from_sat(100_000));
+        match Platform::Bitcoin(data).generate_consolidation_transactions() {
+            Err(InvalidTransaction { reason }) => {
+                assert_eq!(reason, format!("utxos[2] spends {}:0 as utxos[0] does", TXID));
+            },
+            _ => panic!("expected a duplicate outpoint"),
+        }
+    }
+
+    #[test]
+    fn test_consolidation_without_economical_utxo() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_utxo(TXID, 0, Amount::from_sat(100));
+        assert!(matches!(Platform::Bitcoin(data).generate_consolidation_transactions(),
+                         Err(InvalidTransaction { .. })));
+    }
+}
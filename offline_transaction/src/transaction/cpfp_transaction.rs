@@ -0,0 +1,264 @@
+use std::slice;
+use bitcoin::{Amount, Network, OutPoint, PublicKey, ScriptBuf, Transaction, TxIn, TxOut, Witness};
+use bitcoin::absolute::LockTime;
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::transaction::Version;
+use crate::errors::OfflineTransactionError::{InsufficientFunds, InvalidTransaction, MissingParameter};
+use crate::errors::OfflineTransactionResult;
+use crate::frozen::FrozenRegistry;
+use crate::Platform;
+use crate::transaction::generate_transaction::input_sequence;
+use crate::transaction::report::GenerationReport;
+use crate::transaction::TransactionData;
+use crate::types::amount::checked_sum;
+use crate::utils::fee_calculator::{dust_limit, estimate_vsize, INCREMENTAL_RELAY_FEE_RATE};
+use crate::utils::hex_to_transaction::hex_to_transaction;
+use crate::utils::str_to_address::str_to_address;
+use crate::utils::wif_to_private_key::wif_to_private_key;
+
+impl Platform {
+    /// Generates an unsigned child-pays-for-parent transaction for a stuck parent transaction
+    ///
+    /// The parent transaction is taken from `parentTransaction` and its fee from `parentFee`.
+    /// `feeRate` is the target fee rate of the package (parent and child):
+    /// 1. Picks the largest parent output spendable by the private key
+    /// 2. Builds a child spending the output to the change address
+    /// 3. Calculates the child fee to lift the package to the target fee rate from the
+    ///    combined virtual size of the parent and the child
+    ///
+    /// # Returns
+    ///
+    /// Returns a Result containing either:
+    /// * `TransactionData` - Contains the unsigned child transaction and associated data
+    /// * `OfflineTransactionError` - If the child transaction cannot be generated
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * `parentTransaction` or `parentFee` is not specified
+    /// * The parent transaction or the private key cannot be parsed
+    /// * No unfrozen parent output is spendable by the private key
+    /// * The output cannot pay the child fee without the change becoming dust
+    pub fn generate_cpfp_transaction(&self) -> OfflineTransactionResult<TransactionData> {
+        match self {
+            Platform::Bitcoin(params) => {
+                let report = GenerationReport::for_transaction(params)?;
+                let operation = "generate a child-pays-for-parent transaction";
+                let parent = params.parent_transaction()
+                    .ok_or_else(|| MissingParameter {
+                        parameter: "parentTransaction".to_string(),
+                        operation: operation.to_string(),
+                    })
+                    .and_then(hex_to_transaction)?;
+                let parent_fee = params.parent_fee()
+                    .ok_or_else(|| MissingParameter {
+                        parameter: "parentFee".to_string(),
+                        operation: operation.to_string(),
+                    })?;
+
+                let controlled_scripts = controlled_scripts(params.private_key(), params.network())?;
+                let frozen = FrozenRegistry::for_transaction(params)?;
+                let parent_txid = parent.compute_txid();
+                let (vout, parent_output) = parent.output.iter()
+                    .enumerate()
+                    .filter(|(_, output)| controlled_scripts.contains(&output.script_pubkey))
+                    .filter(|(vout, _)| frozen.find(&OutPoint::new(parent_txid, *vout as u32)).is_none())
+                    .max_by_key(|(_, output)| output.value)
+                    .ok_or_else(|| InvalidTransaction {
+                        reason: "the parent transaction has no unfrozen output spendable by the private key".to_string(),
+                    })?;
+
+                let change_address = str_to_address(params.change_address(), params.network(), "changeAddress")?;
+                let mut child_output = TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: change_address.script_pubkey(),
+                };
+                let child_vsize = estimate_vsize(
+                    slice::from_ref(&parent_output.script_pubkey),
+                    slice::from_ref(&child_output));
+                let child_fee = Amount::from_sat(child_fee(
+                    parent_fee, parent.vsize() as u64, child_vsize, report.fee_rate)?);
+
+                // The child output has to keep at least the dust limit
+                let dust = Amount::from_sat(dust_limit(&child_output.script_pubkey));
+                let Some(child_amount) = parent_output.value.checked_sub(child_fee)
+                    .filter(|amount| *amount >= dust) else {
+                    let required = checked_sum([dust, child_fee], "output amounts and the fee")?;
+                    return Err(InsufficientFunds {
+                        shortfall: required.checked_sub(parent_output.value).unwrap_or(Amount::ZERO),
+                        total_in: parent_output.value,
+                        total_out: dust,
+                        fee: child_fee,
+                    });
+                };
+                child_output.value = child_amount;
+
+                let child = Transaction {
+                    version: Version::TWO,
+                    lock_time: params.lock_time()
+                        .map(LockTime::from_consensus)
+                        .unwrap_or(LockTime::ZERO),
+                    input: vec![TxIn {
+                        previous_output: OutPoint::new(parent_txid, vout as u32),
+                        script_sig: ScriptBuf::new(),
+                        sequence: input_sequence(params.rbf(), params.lock_time().is_some()),
+                        witness: Witness::new(),
+                    }],
+                    output: vec![child_output],
+                };
+
+                Ok(TransactionData::BitcoinTransaction {
+                    raw_transaction: child,
+                    signed: false,
+                    input_data: vec![(parent_output.value.to_sat(), parent_output.script_pubkey.clone())],
+                    private_key: params.private_key().to_string(),
+                    network: params.network(),
+                    report,
+                })
+            }
+        }
+    }
+}
+
+/// Derives the script_pubkeys which can be signed with the private key
+///
+/// Only the script types supported by `sign_transaction` (P2PKH and P2WPKH) are returned,
+/// and only P2PKH for an uncompressed key.
+fn controlled_scripts(private_key: &str, network: Network) -> OfflineTransactionResult<Vec<ScriptBuf>> {
+    let secp = Secp256k1::new();
+    let private_key = wif_to_private_key(private_key, network)?;
+    let public_key = PublicKey::from_private_key(&secp, &private_key);
+
+    let mut scripts = vec![ScriptBuf::new_p2pkh(&public_key.pubkey_hash())];
+    if let Ok(wpubkey_hash) = public_key.wpubkey_hash() {
+        scripts.push(ScriptBuf::new_p2wpkh(&wpubkey_hash));
+    }
+
+    Ok(scripts)
+}
+
+/// Calculates the child fee lifting the parent and child package to the target fee rate
+///
+/// If the parent already pays more than the target fee rate, the child pays the
+/// target fee rate for its own size. The child always pays at least the minimum relay fee.
+fn child_fee(parent_fee: u64,
+             parent_vsize: u64,
+             child_vsize: u64,
+             fee_rate: u64) -> OfflineTransactionResult<u64> {
+    let overflow = || InvalidTransaction {
+        reason: format!("child fee overflows at fee rate {} sat/vB", fee_rate),
+    };
+    let package_fee = parent_vsize.checked_add(child_vsize)
+        .and_then(|package_vsize| package_vsize.checked_mul(fee_rate))
+        .ok_or_else(overflow)?;
+    let own_fee = child_vsize.checked_mul(fee_rate.max(INCREMENTAL_RELAY_FEE_RATE))
+        .ok_or_else(overflow)?;
+
+    Ok(package_fee.saturating_sub(parent_fee).max(own_fee))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Network;
+    use crate::types::bitcoin::BitcoinTransactionData;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    const SENDER: &str = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa";
+    const PRIVATE_KEY: &str = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+    const TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+
+    /// A deposit paying 0.005 BTC to `ADDRESS` with a 0.00495 BTC change back to the sender
+    fn parent_transaction() -> Transaction {
+        let script = |address: &str| str_to_address(address, Network::Bitcoin, "address").unwrap().script_pubkey();
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(TXID.parse().unwrap(), 0),
+                script_sig: ScriptBuf::new(),
+                sequence: input_sequence(true, false),
+                witness: Witness::new(),
+            }],
+            output: vec![
+                TxOut { value: Amount::from_sat(495_000), script_pubkey: script(SENDER) },
+                TxOut { value: Amount::from_sat(500_000), script_pubkey: script(ADDRESS) },
+            ],
+        }
+    }
+
+    fn cpfp_data(parent: &Transaction, parent_fee: u64, fee_rate: u64) -> BitcoinTransactionData {
+        let raw_transaction = bitcoin::consensus::encode::serialize_hex(parent);
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.set_fee_rate(fee_rate)
+            .set_parent_transaction(&raw_transaction, parent_fee);
+        data
+    }
+
+    #[test]
+    fn test_cpfp_lifts_package_fee_rate() {
+        let parent = parent_transaction();
+        let platform = Platform::Bitcoin(cpfp_data(&parent, 100, 20));
+
+        match platform.generate_cpfp_transaction().unwrap() {
+            TransactionData::BitcoinTransaction { raw_transaction, input_data, .. } => {
+                assert_eq!(raw_transaction.input[0].previous_output,
+                           OutPoint::new(parent.compute_txid(), 1));
+                assert_eq!(input_data[0].0, 500_000);
+
+                let child_fee = 500_000 - raw_transaction.output[0].value.to_sat();
+                let child_vsize = estimate_vsize(&[input_data[0].1.clone()], &raw_transaction.output);
+                assert_eq!(child_fee + 100, (parent.vsize() as u64 + child_vsize) * 20);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cpfp_parent_already_above_target() {
+        let parent = parent_transaction();
+        let platform = Platform::Bitcoin(cpfp_data(&parent, 100_000, 2));
+
+        match platform.generate_cpfp_transaction().unwrap() {
+            TransactionData::BitcoinTransaction { raw_transaction, input_data, .. } => {
+                let child_fee = 500_000 - raw_transaction.output[0].value.to_sat();
+                let child_vsize = estimate_vsize(&[input_data[0].1.clone()], &raw_transaction.output);
+                assert_eq!(child_fee, child_vsize * 2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cpfp_output_cannot_pay_child_fee() {
+        let parent = parent_transaction();
+        let platform = Platform::Bitcoin(cpfp_data(&parent, 100, 5_000));
+        match platform.generate_cpfp_transaction() {
+            Err(InsufficientFunds { shortfall, total_in, total_out, fee }) => {
+                assert_eq!(total_in, Amount::from_sat(500_000));
+                assert_eq!(shortfall, total_out + fee - total_in);
+            }
+            _ => panic!("expected insufficient funds"),
+        }
+    }
+
+    #[test]
+    fn test_cpfp_child_fee_beyond_money_supply() {
+        let parent = parent_transaction();
+        let platform = Platform::Bitcoin(cpfp_data(&parent, 100, 100_000_000_000_000));
+        assert!(matches!(platform.generate_cpfp_transaction(), Err(InvalidTransaction { .. })));
+    }
+
+    #[test]
+    fn test_cpfp_no_controlled_output() {
+        let mut parent = parent_transaction();
+        parent.output.pop();
+        let platform = Platform::Bitcoin(cpfp_data(&parent, 100, 20));
+        assert!(matches!(platform.generate_cpfp_transaction(), Err(InvalidTransaction { .. })));
+    }
+
+    #[test]
+    fn test_cpfp_missing_parent() {
+        let platform = Platform::Bitcoin(BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY));
+        assert!(matches!(platform.generate_cpfp_transaction(), Err(MissingParameter { .. })));
+    }
+}
@@ -0,0 +1,416 @@
+use bitcoin::{Address, Amount, Network, Script, Transaction};
+use bitcoin::consensus::encode;
+use bitcoin::opcodes::{Class, ClassifyContext};
+use bitcoin::script::Instruction;
+use serde::{Serialize, Serializer};
+use serde::ser::Error;
+use serde_json::value::RawValue;
+use crate::errors::OfflineTransactionResult;
+use crate::transaction::TransactionData;
+use crate::utils::hex_to_transaction::hex_to_transaction;
+
+/// A transaction decoded as by `bitcoin-cli decoderawtransaction`
+///
+/// # Fields
+///
+/// * `txid` - Transaction ID
+/// * `hash` - Witness transaction ID (wtxid), equal to the txid without witness
+/// * `version` - Transaction version
+/// * `size` - Serialized size in bytes
+/// * `vsize` - Virtual size in vbytes
+/// * `weight` - Weight in weight units
+/// * `locktime` - Absolute lock time
+/// * `vin` - The inputs
+/// * `vout` - The outputs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedTransaction {
+    pub txid: String,
+    pub hash: String,
+    pub version: i32,
+    pub size: usize,
+    pub vsize: usize,
+    pub weight: u64,
+    pub locktime: u32,
+    pub vin: Vec<DecodedInput>,
+    pub vout: Vec<DecodedOutput>,
+}
+
+/// A decoded input
+///
+/// # Fields
+///
+/// * `coinbase` - The script_sig in hex of a coinbase input, which spends no output
+/// * `txid` - Transaction ID of the spent output
+/// * `vout` - Output index of the spent output
+/// * `script_sig` - The script_sig, empty for native segwit inputs
+/// * `txinwitness` - The witness items in hex
+/// * `sequence` - The sequence number
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedInput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coinbase: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub txid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vout: Option<u32>,
+    #[serde(rename = "scriptSig", skip_serializing_if = "Option::is_none")]
+    pub script_sig: Option<DecodedScript>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub txinwitness: Vec<String>,
+    pub sequence: u32,
+}
+
+/// A decoded output
+///
+/// # Fields
+///
+/// * `value` - Amount, serialized in BTC with 8 decimals such as `0.00000546`
+/// * `n` - Output index
+/// * `script_pub_key` - The script_pubkey
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedOutput {
+    #[serde(serialize_with = "serialize_btc")]
+    pub value: Amount,
+    pub n: usize,
+    #[serde(rename = "scriptPubKey")]
+    pub script_pub_key: DecodedScript,
+}
+
+/// A decoded script
+///
+/// # Fields
+///
+/// * `asm` - The script in assembly as Bitcoin Core writes it: pushes of up to 4 bytes as numbers,
+///   longer pushes as bare hex, and signatures of a script_sig with their sighash type such as `[ALL]`
+/// * `hex` - The script in hex
+/// * `address` - The address of an output script for the network, if it has one
+/// * `script_type` - The type of an output script, such as `witness_v0_keyhash`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedScript {
+    pub asm: String,
+    pub hex: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub script_type: Option<String>,
+}
+
+/// Decodes a raw transaction as by `bitcoin-cli decoderawtransaction`
+///
+/// Any transaction can be decoded, not only the ones generated by this crate.
+///
+/// # Arguments
+///
+/// * `raw_transaction` - The transaction encoded as a hexadecimal string
+/// * `network` - The network whose addresses the output scripts are displayed as
+///
+/// # Returns
+///
+/// * `OfflineTransactionResult<DecodedTransaction>` - The decoded transaction, or an error if the hex
+///   is not a transaction
+pub fn decode_raw_transaction(raw_transaction: &str, network: Network) -> OfflineTransactionResult<DecodedTransaction> {
+    Ok(decode_transaction(&hex_to_transaction(raw_transaction)?, network))
+}
+
+impl TransactionData {
+    /// Decodes the transaction as by `bitcoin-cli decoderawtransaction`
+    ///
+    /// # Returns
+    ///
+    /// * `DecodedTransaction` - The decoded transaction, with the addresses of its network
+    pub fn decode(&self) -> DecodedTransaction {
+        match self {
+            TransactionData::BitcoinTransaction { raw_transaction, network, .. } => {
+                decode_transaction(raw_transaction, *network)
+            }
+        }
+    }
+}
+
+fn decode_transaction(transaction: &Transaction, network: Network) -> DecodedTransaction {
+    let vin = transaction.input.iter()
+        .map(|input| {
+            let witness = input.witness.iter().map(hex::encode).collect();
+            if transaction.is_coinbase() {
+                DecodedInput {
+                    coinbase: Some(input.script_sig.to_hex_string()),
+                    txid: None,
+                    vout: None,
+                    script_sig: None,
+                    txinwitness: witness,
+                    sequence: input.sequence.to_consensus_u32(),
+                }
+            } else {
+                DecodedInput {
+                    coinbase: None,
+                    txid: Some(input.previous_output.txid.to_string()),
+                    vout: Some(input.previous_output.vout),
+                    script_sig: Some(DecodedScript {
+                        asm: script_asm(&input.script_sig, true),
+                        hex: input.script_sig.to_hex_string(),
+                        address: None,
+                        script_type: None,
+                    }),
+                    txinwitness: witness,
+                    sequence: input.sequence.to_consensus_u32(),
+                }
+            }
+        })
+        .collect();
+
+    let vout = transaction.output.iter()
+        .enumerate()
+        .map(|(n, output)| DecodedOutput {
+            value: output.value,
+            n,
+            script_pub_key: DecodedScript {
+                asm: script_asm(&output.script_pubkey, false),
+                hex: output.script_pubkey.to_hex_string(),
+                address: Address::from_script(&output.script_pubkey, network)
+                    .ok()
+                    .map(|address| address.to_string()),
+                script_type: Some(script_type(&output.script_pubkey).to_string()),
+            },
+        })
+        .collect();
+
+    DecodedTransaction {
+        txid: transaction.compute_txid().to_string(),
+        hash: transaction.compute_wtxid().to_string(),
+        version: transaction.version.0,
+        size: encode::serialize(transaction).len(),
+        vsize: transaction.vsize(),
+        weight: transaction.weight().to_wu(),
+        locktime: transaction.lock_time.to_consensus_u32(),
+        vin,
+        vout,
+    }
+}
+
+/// Writes a script in assembly as Bitcoin Core does
+///
+/// Pushes of up to 4 bytes are written as script numbers and longer pushes as bare hex.
+/// With `decode_sighash`, pushes encoded as signatures get their sighash type instead of its byte.
+/// The rest of a script which cannot be parsed is written as `[error]`.
+fn script_asm(script: &Script, decode_sighash: bool) -> String {
+    let decode_sighash = decode_sighash && !script.is_op_return();
+    let mut asm = Vec::new();
+
+    for instruction in script.instructions() {
+        match instruction {
+            Ok(Instruction::PushBytes(bytes)) => {
+                let bytes = bytes.as_bytes();
+                if bytes.len() <= 4 {
+                    asm.push(script_number(bytes).to_string());
+                    continue;
+                }
+                match (decode_sighash, bytes.split_last()) {
+                    (true, Some((sighash, signature))) if is_valid_signature_encoding(bytes) => {
+                        match sighash_name(*sighash) {
+                            Some(name) => asm.push(format!("{}[{}]", hex::encode(signature), name)),
+                            None => asm.push(hex::encode(bytes)),
+                        }
+                    },
+                    _ => asm.push(hex::encode(bytes)),
+                }
+            },
+            Ok(Instruction::Op(opcode)) => {
+                let name = match (opcode.classify(ClassifyContext::Legacy), opcode.to_u8()) {
+                    (Class::PushNum(number), _) => number.to_string(),
+                    // Bitcoin Core names these opcodes differently
+                    (_, 0xb1) => "OP_CHECKLOCKTIMEVERIFY".to_string(),
+                    (_, 0xb2) => "OP_CHECKSEQUENCEVERIFY".to_string(),
+                    (_, 0xbb..=0xfe) => "OP_UNKNOWN".to_string(),
+                    _ => opcode.to_string(),
+                };
+                asm.push(name);
+            },
+            Err(_) => {
+                asm.push("[error]".to_string());
+                break;
+            },
+        }
+    }
+
+    asm.join(" ")
+}
+
+/// Decodes a push of up to 4 bytes as a little-endian sign-magnitude script number
+fn script_number(bytes: &[u8]) -> i64 {
+    let Some(last) = bytes.last() else {
+        return 0;
+    };
+    let value = bytes.iter()
+        .enumerate()
+        .fold(0i64, |value, (index, byte)| value | (i64::from(*byte) << (8 * index)));
+
+    if last & 0x80 != 0 {
+        -(value & !(0x80i64 << (8 * (bytes.len() - 1))))
+    } else {
+        value
+    }
+}
+
+/// Names a sighash type as Bitcoin Core does, if it is defined
+fn sighash_name(sighash: u8) -> Option<&'static str> {
+    match sighash {
+        0x01 => Some("ALL"),
+        0x02 => Some("NONE"),
+        0x03 => Some("SINGLE"),
+        0x81 => Some("ALL|ANYONECANPAY"),
+        0x82 => Some("NONE|ANYONECANPAY"),
+        0x83 => Some("SINGLE|ANYONECANPAY"),
+        _ => None,
+    }
+}
+
+/// Checks the strict DER encoding of a signature followed by its sighash byte (BIP66)
+fn is_valid_signature_encoding(signature: &[u8]) -> bool {
+    let len = signature.len();
+    if !(9..=73).contains(&len) || signature[0] != 0x30 || usize::from(signature[1]) != len - 3 {
+        return false;
+    }
+
+    let len_r = usize::from(signature[3]);
+    if 5 + len_r >= len {
+        return false;
+    }
+    let len_s = usize::from(signature[5 + len_r]);
+    if len_r + len_s + 7 != len {
+        return false;
+    }
+
+    // R and S are positive integers without unnecessary leading zeros
+    let valid_integer = |start: usize, int_len: usize| signature[start - 2] == 0x02
+        && int_len != 0
+        && signature[start] & 0x80 == 0
+        && !(int_len > 1 && signature[start] == 0 && signature[start + 1] & 0x80 == 0);
+    valid_integer(4, len_r) && valid_integer(len_r + 6, len_s)
+}
+
+/// Names the type of an output script as Bitcoin Core does
+fn script_type(script: &Script) -> &'static str {
+    if script.is_p2pkh() {
+        "pubkeyhash"
+    } else if script.is_p2sh() {
+        "scripthash"
+    } else if script.is_p2wpkh() {
+        "witness_v0_keyhash"
+    } else if script.is_p2wsh() {
+        "witness_v0_scripthash"
+    } else if script.is_p2tr() {
+        "witness_v1_taproot"
+    } else if script.is_witness_program() {
+        "witness_unknown"
+    } else if script.is_p2pk() {
+        "pubkey"
+    } else if script.is_multisig() {
+        "multisig"
+    } else if script.is_op_return() {
+        "nulldata"
+    } else {
+        "nonstandard"
+    }
+}
+
+/// Serializes an amount as a number in BTC with 8 decimals, as Bitcoin Core does
+///
+/// An `f64` would serialize small amounts with an exponent, such as `5.46e-6` for the dust limit.
+fn serialize_btc<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+    let sat = amount.to_sat();
+    let value = RawValue::from_string(format!("{}.{:08}", sat / 100_000_000, sat % 100_000_000))
+        .map_err(S::Error::custom)?;
+    value.serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The first bitcoin transaction, from block 170
+    const BLOCK_170_TRANSACTION: &str = "0100000001c997a5e56e104102fa209c6a852dd90660a20b2d9c352423edce25857fcd3704000000004847304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d0901ffffffff0200ca9a3b00000000434104ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84cac00286bee0000000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac00000000";
+
+    #[test]
+    fn test_decode_legacy_transaction() {
+        let decoded = decode_raw_transaction(BLOCK_170_TRANSACTION, Network::Bitcoin).unwrap();
+        assert_eq!(decoded.txid, "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16");
+        assert_eq!(decoded.hash, decoded.txid);
+        assert_eq!((decoded.version, decoded.size, decoded.vsize, decoded.weight, decoded.locktime), (1, 275, 275, 1100, 0));
+        assert_eq!(decoded.vin[0].vout, Some(0));
+        assert_eq!(decoded.vin[0].sequence, 0xffff_ffff);
+        assert_eq!(decoded.vin[0].script_sig.as_ref().unwrap().asm, "304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732\
+            e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d09[ALL]");
+        assert_eq!(decoded.vout[0].script_pub_key.asm, "04ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa\
+            28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84c OP_CHECKSIG");
+        assert_eq!(decoded.vout[0].value, Amount::from_int_btc(10));
+        assert_eq!(decoded.vout[1].value, Amount::from_int_btc(40));
+        assert_eq!(decoded.vout[0].script_pub_key.script_type.as_deref(), Some("pubkey"));
+
+    }
+
+    #[test]
+    fn test_decode_segwit_transaction() {
+        use crate::Platform;
+        use crate::types::bitcoin::BitcoinTransactionData;
+
+        let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        let mut data = BitcoinTransactionData::new(address, "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn");
+        data.push_input("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16", 1, "0.01")
+            .push_output("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", "0.005");
+        let mut transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        transaction.sign_transaction().unwrap();
+
+        let decoded = transaction.decode();
+        assert_ne!(decoded.hash, decoded.txid);
+        assert_eq!(decoded.vin[0].txinwitness.len(), 2);
+        assert_eq!(decoded.vin[0].script_sig.as_ref().unwrap().hex, "");
+        assert_eq!(decoded.vin[0].sequence, 0xffff_fffd);
+        assert_eq!(decoded.vout[0].value, Amount::from_sat(500_000));
+        assert_eq!(decoded.vout[0].script_pub_key.address.as_deref(), Some("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"));
+        assert_eq!(decoded.vout[0].script_pub_key.script_type.as_deref(), Some("pubkeyhash"));
+        assert_eq!(decoded.vout[1].script_pub_key.address.as_deref(), Some(address));
+        assert_eq!(decoded.vout[1].script_pub_key.script_type.as_deref(), Some("witness_v0_keyhash"));
+        assert_eq!(decoded.vout[1].script_pub_key.asm, "0 751e76e8199196d454941c45d1b3a323f1433bd6");
+
+        let json = serde_json::to_value(&decoded).unwrap();
+        assert!(json["vin"][0].get("coinbase").is_none());
+        assert_eq!(json["vout"][1]["scriptPubKey"]["type"], "witness_v0_keyhash");
+        assert!(decode_raw_transaction("00", Network::Bitcoin).is_err());
+    }
+
+    #[test]
+    fn test_decode_values_as_bitcoin_core() {
+        let output = |sat| DecodedOutput {
+            value: Amount::from_sat(sat),
+            n: 0,
+            script_pub_key: DecodedScript { asm: String::new(), hex: String::new(), address: None, script_type: None },
+        };
+
+        let json = |sat| serde_json::to_string(&output(sat)).unwrap();
+        assert!(json(546).starts_with(r#"{"value":0.00000546,"#));
+        assert!(json(500_000).starts_with(r#"{"value":0.00500000,"#));
+        assert!(json(4_000_000_000).starts_with(r#"{"value":40.00000000,"#));
+    }
+
+    #[test]
+    fn test_script_asm_as_bitcoin_core() {
+        use bitcoin::opcodes::all::{OP_CHECKSIG, OP_CSV, OP_DROP, OP_PUSHNUM_16, OP_PUSHNUM_NEG1};
+        use bitcoin::script::Builder;
+
+        let script = Builder::new()
+            .push_int(0)
+            .push_opcode(OP_PUSHNUM_16)
+            .push_opcode(OP_PUSHNUM_NEG1)
+            .push_slice([0x81])
+            .push_slice([0x10, 0x27])
+            .push_opcode(OP_CSV)
+            .push_opcode(OP_DROP)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        assert_eq!(script_asm(&script, false), "0 16 -1 -1 10000 OP_CHECKSEQUENCEVERIFY OP_DROP OP_CHECKSIG");
+
+        // A push running past the end of the script
+        let truncated = bitcoin::ScriptBuf::from_bytes(vec![0x76, 0x05, 0x01]);
+        assert_eq!(script_asm(&truncated, false), "OP_DUP [error]");
+    }
+}
@@ -1,23 +1,31 @@
 use std::str::FromStr;
-use bitcoin::{Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+use bitcoin::{Amount, Denomination, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
 use bitcoin::absolute::LockTime;
 use bitcoin::transaction::Version;
-use crate::errors::OfflineTransactionError::ParseError;
+use crate::errors::OfflineTransactionError::{InsufficientFunds, InvalidTransaction, ParseError};
 use crate::errors::OfflineTransactionResult;
+use crate::coin_selection::{select_coins, CoinSelectionStrategy};
+use crate::frozen::FrozenRegistry;
 use crate::Platform;
+use crate::transaction::ordering::order_transaction;
+use crate::transaction::report::{DroppedChangeReport, GenerationReport, InterpretedAmount};
 use crate::transaction::TransactionData;
-use crate::utils::btc_sat_trans::btc_to_sat;
-use crate::utils::fee_calculator::fee_calculator;
-use crate::utils::str_to_address::str_to_address_unchecked;
+use crate::types::amount::{checked_sum, BitcoinAmount};
+use crate::utils::fee_calculator::{change_cost, dust_limit, fee_calculator};
+use crate::utils::str_to_address::str_to_address;
 
 impl Platform {
     /// Generates an unsigned transaction based on the platform parameters
     ///
     /// Creates a new unsigned transaction with the following general steps:
-    /// 1. Converts input transactions to appropriate transaction inputs
-    /// 2. Converts output specifications to transaction outputs
-    /// 3. Calculates the transaction fee based on the fee rate
-    /// 4. Adds a change output if the remaining amount is greater than the minimum threshold
+    /// 1. Converts output specifications to transaction outputs
+    /// 2. Selects the inputs from the UTXO pool if no input is specified, leaving out frozen UTXOs
+    /// 3. Converts input transactions to appropriate transaction inputs
+    /// 4. Calculates the transaction fee based on the fee rate
+    /// 5. Adds a change output if the remaining amount exceeds the cost of change and the change
+    ///    is not dust for the change script, otherwise the remaining amount is added to the fee.
+    ///    The change is split into several outputs if requested, each paying for its own size
+    /// 6. Orders the inputs and outputs as requested
     ///
     /// # Returns
     ///
@@ -31,14 +39,95 @@ impl Platform {
     /// * Input transaction IDs cannot be parsed
     /// * Addresses cannot be parsed
     /// * Fee calculation fails
+    /// * The UTXO pool cannot fund the outputs and the fee
+    /// * An input is frozen in the frozen UTXO registry
+    /// * An outpoint is spent twice
     pub fn generate_unsigned_transaction(&self) -> OfflineTransactionResult<TransactionData> {
         match self {
             Platform::Bitcoin(params) => {
+                self.generate_unsigned_transaction_with(params.coin_selection().strategy().as_ref())
+            }
+        }
+    }
+
+    /// Generates an unsigned transaction selecting the inputs with the given coin selection strategy
+    ///
+    /// This is [`Platform::generate_unsigned_transaction`] with a custom strategy instead of the one
+    /// specified in the transaction data. The strategy is only used if the inputs are selected from
+    /// the UTXO pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `strategy` - The coin selection strategy
+    ///
+    /// # Returns
+    ///
+    /// * `OfflineTransactionResult<TransactionData>` - The unsigned transaction, or an error
+    ///   as for [`Platform::generate_unsigned_transaction`]
+    pub fn generate_unsigned_transaction_with(&self, strategy: &dyn CoinSelectionStrategy)
+        -> OfflineTransactionResult<TransactionData> {
+        match self {
+            Platform::Bitcoin(params) => {
+                let mut report = GenerationReport::for_transaction(params)?;
+
+                let mut tx_outputs: Vec<TxOut> = Vec::new();
+                for (index, output) in params.outputs().iter().enumerate() {
+                    let address = str_to_address(
+                        output.address(), params.network(), &format!("outputs[{}].address", index))?;
+                    let script_pubkey = address.script_pubkey();
+                    // Paying the same script twice is valid, but most likely a mistake
+                    if let Some(first) = tx_outputs.iter().position(|other| other.script_pubkey == script_pubkey) {
+                        report.warnings.push(format!("outputs[{}] pays {} as outputs[{}] does",
+                                                     index, output.address(), first));
+                    }
+
+                    let output_amount = interpret_amount(
+                        &mut report, format!("outputs[{}].amount", index), output.amount(), params.amount_unit())?;
+
+                    tx_outputs.push(TxOut {
+                        value: output_amount,
+                        script_pubkey,
+                    });
+                }
+                let total_output_amount = checked_sum(tx_outputs.iter().map(|output| output.value), "output amounts")?;
+
+                let change_address = str_to_address(params.change_address(), params.network(), "changeAddress")?;
+                let frozen = FrozenRegistry::for_transaction(params)?;
+                // Without inputs, they are selected from the UTXO pool.
+                let (inputs, changeless) = if params.inputs().is_empty() && !params.utxos().is_empty() {
+                    // Frozen UTXOs of the pool are never selected
+                    report.frozen_utxos = params.utxos().iter()
+                        .filter(|utxo| frozen.is_frozen(utxo.txid(), utxo.vout()))
+                        .map(|utxo| format!("{}:{}", utxo.txid(), utxo.vout()))
+                        .collect();
+                    let (indices, selection_report) = select_coins(
+                        params,
+                        &frozen,
+                        &tx_outputs,
+                        &change_address.script_pubkey(),
+                        report.fee_rate,
+                        strategy)?;
+                    let changeless = selection_report.changeless;
+                    report.coin_selection = Some(selection_report);
+
+                    let inputs = indices.iter()
+                        .map(|index| (format!("utxos[{}]", index), &params.utxos()[*index]))
+                        .collect::<Vec<_>>();
+                    (inputs, changeless)
+                } else {
+                    let inputs = params.inputs().iter()
+                        .enumerate()
+                        .map(|(index, input)| (format!("inputs[{}]", index), input))
+                        .collect::<Vec<_>>();
+                    (inputs, false)
+                };
+
                 let mut tx_inputs = Vec::new();
                 let mut input_details = Vec::new();
-                let mut total_input_amount: u64 = 0;
+                let mut input_fields: Vec<String> = Vec::new();
+                let sequence = input_sequence(params.rbf(), params.lock_time().is_some());
 
-                for input in params.inputs() {
+                for (field, input) in inputs {
                     let txid = Txid::from_str(input.txid())
                         .map_err(|e| ParseError {
                             from: "string".to_string(),
@@ -46,68 +135,581 @@ impl Platform {
                             reason: e.to_string()
                         })?;
                     let outpoint = OutPoint::new(txid, input.vout());
+                    frozen.check(&outpoint)?;
+                    // Spending an output twice makes the transaction invalid by consensus
+                    if let Some(first) = tx_inputs.iter().position(|other: &TxIn| other.previous_output == outpoint) {
+                        return Err(InvalidTransaction {
+                            reason: format!("{} spends {} as {} does", field, outpoint, input_fields[first]),
+                        });
+                    }
 
                     tx_inputs.push(TxIn {
                         previous_output: outpoint,
                         script_sig: ScriptBuf::new(),
                         // Currently, limitation of the relative lock time is unsupported.
-                        sequence: Sequence::MAX,
+                        sequence,
                         witness: Witness::new(),
                     });
 
-                    let input_amound_sat = btc_to_sat(input.amount());
-                    let address = str_to_address_unchecked(input.address())?;
+                    let input_amount = interpret_amount(
+                        &mut report, format!("{}.amount", field), input.amount(), params.amount_unit())?;
+                    let address = str_to_address(input.address(), params.network(), &format!("{}.address", field))?;
 
-                    input_details.push((input_amound_sat, address.script_pubkey()));
-                    total_input_amount += input_amound_sat;
+                    input_details.push((input_amount.to_sat(), address.script_pubkey()));
+                    input_fields.push(field);
                 }
+                let total_input_amount = checked_sum(
+                    input_details.iter().map(|(amount, _)| Amount::from_sat(*amount)), "input amounts")?;
 
-                let mut tx_outputs = Vec::new();
-                let mut total_output_amount: u64 = 0;
-
-                for output in params.outputs() {
-                    let address = str_to_address_unchecked(output.address())?;
-
-                    let output_amount_sat = btc_to_sat(output.amount());
+                let input_scripts = input_details.iter()
+                    .map(|(_, script_pubkey)| script_pubkey.clone())
+                    .collect::<Vec<_>>();
+                let transaction_fee = Amount::from_sat(fee_calculator(
+                    &input_scripts,
+                    &tx_outputs,
+                    report.fee_rate)?);
+                let required = checked_sum([total_output_amount, transaction_fee], "output amounts and the fee")?;
+                let excess = total_input_amount.checked_sub(required)
+                    .ok_or_else(|| InsufficientFunds {
+                        shortfall: required.checked_sub(total_input_amount).unwrap_or(Amount::ZERO),
+                        total_in: total_input_amount,
+                        total_out: total_output_amount,
+                        fee: transaction_fee,
+                    })?
+                    .to_sat();
 
-                    tx_outputs.push(TxOut {
-                        value: Amount::from_sat(output_amount_sat),
-                        script_pubkey: address.script_pubkey(),
-                    });
+                // The change output pays for its own size
+                let change_script = change_address.script_pubkey();
+                let (change_fee, cost_of_change) = change_cost(
+                    &change_script, report.fee_rate, params.long_term_fee_rate())?;
+                let change_amount = excess.saturating_sub(change_fee);
+                let change_dust_limit = dust_limit(&change_script);
 
-                    total_output_amount += output_amount_sat;
-                }
+                let dropped_reason = if changeless {
+                    Some("the coin selection is changeless".to_string())
+                } else if excess <= cost_of_change {
+                    Some(format!("the excess does not exceed the cost of change of {} sat", cost_of_change))
+                } else if change_amount < change_dust_limit {
+                    Some(format!("the change of {} sat is under the dust limit of {} sat",
+                                 change_amount, change_dust_limit))
+                } else {
+                    None
+                };
 
-                let transaction_fee = fee_calculator(
-                    &tx_inputs,
-                    &tx_outputs,
-                    params.fee_rate())?;
-                let change_amount = total_input_amount - total_output_amount - transaction_fee;
+                let mut change_count = 0;
+                match dropped_reason {
+                    None => {
+                        let change_amounts = match params.change_split() {
+                            Some(change_split) => change_split.amounts(
+                                excess, change_fee, change_dust_limit, params.amount_unit())?,
+                            None => vec![change_amount],
+                        };
+                        change_count = change_amounts.len();
+                        tx_outputs.extend(change_amounts.into_iter().map(|amount| TxOut {
+                            value: Amount::from_sat(amount),
+                            script_pubkey: change_script.clone(),
+                        }));
 
-                // Under 546 satoshi, the change will be treated as dust.
-                if change_amount > 546 {
-                    let change_address = str_to_address_unchecked(params.change_address())?;
-                    tx_outputs.push(TxOut {
-                        value: Amount::from_sat(change_amount),
-                        script_pubkey: change_address.script_pubkey(),
-                    });
+                        // The output count may grow by several bytes with many change outputs
+                        let fee = Amount::from_sat(fee_calculator(&input_scripts, &tx_outputs, report.fee_rate)?);
+                        let total_amount = checked_sum(
+                            tx_outputs.iter().map(|output| output.value).chain([fee]), "output amounts and the fee")?;
+                        let shortfall = total_amount.checked_sub(total_input_amount).unwrap_or(Amount::ZERO);
+                        if let Some(last) = tx_outputs.last_mut() {
+                            last.value = last.value.checked_sub(shortfall)
+                                .filter(|value| value.to_sat() >= change_dust_limit)
+                                .ok_or_else(|| InvalidTransaction {
+                                    reason: format!("the change outputs cannot pay their fee of {} sat", fee.to_sat()),
+                                })?;
+                        }
+                    },
+                    Some(reason) if excess > 0 => {
+                        report.dropped_change = Some(DroppedChangeReport { amount: excess, reason });
+                    },
+                    Some(_) => {},
                 }
 
-                let tx = Transaction {
+                let mut tx = Transaction {
                     version: Version::TWO,
-                    // Lock time unsupported
-                    lock_time: LockTime::ZERO,
+                    lock_time: params.lock_time()
+                        .map(LockTime::from_consensus)
+                        .unwrap_or(LockTime::ZERO),
                     input: tx_inputs,
                     output: tx_outputs,
                 };
+                report.change_indices = order_transaction(
+                    &mut tx, &mut input_details, change_count, params.output_ordering(), params.ordering_seed(),
+                    params.private_key());
+                if let Some(coin_selection) = report.coin_selection.as_mut() {
+                    // The ordering may have reordered the inputs
+                    coin_selection.selected.sort_by_key(|utxo| {
+                        tx.input.iter().position(|input| {
+                            input.previous_output.vout == utxo.vout
+                                && Txid::from_str(utxo.txid.trim()).is_ok_and(|txid| txid == input.previous_output.txid)
+                        })
+                    });
+                }
 
                 Ok(TransactionData::BitcoinTransaction {
                     raw_transaction: tx,
                     signed: false,
                     input_data: input_details,
                     private_key: params.private_key().to_string(),
+                    network: params.network(),
+                    report,
                 })
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Interprets an amount of the request and records it in the report
+///
+/// # Arguments
+///
+/// * `report` - The generation report
+/// * `field` - Path of the amount in the input JSON
+/// * `amount` - The amount as given
+/// * `amount_unit` - Unit of the amounts given without a unit
+///
+/// # Returns
+///
+/// * `OfflineTransactionResult<Amount>` - The amount, or an error if the amount is invalid
+fn interpret_amount(report: &mut GenerationReport,
+                    field: String,
+                    amount: &BitcoinAmount,
+                    amount_unit: Denomination) -> OfflineTransactionResult<Amount> {
+    let interpreted = amount.resolve(&field, amount_unit)?;
+    report.amounts.push(InterpretedAmount { field, given: amount.to_string(), sat: interpreted.to_sat() });
+
+    Ok(interpreted)
+}
+
+/// Chooses the sequence number applied to every input
+///
+/// Any sequence below `0xFFFFFFFE` signals BIP125 replaceability and also enables
+/// the absolute lock time, so RBF is expressed with `ENABLE_RBF_NO_LOCKTIME` whether
+/// a lock time is set or not. Without RBF, `ENABLE_LOCKTIME_NO_RBF` keeps a requested
+/// lock time effective while `MAX` disables both.
+///
+/// # Arguments
+///
+/// * `rbf` - Whether the transaction signals replace-by-fee
+/// * `has_lock_time` - Whether an absolute lock time is requested
+pub(crate) fn input_sequence(rbf: bool, has_lock_time: bool) -> Sequence {
+    match (rbf, has_lock_time) {
+        (true, _) => Sequence::ENABLE_RBF_NO_LOCKTIME,
+        (false, true) => Sequence::ENABLE_LOCKTIME_NO_RBF,
+        (false, false) => Sequence::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Network;
+    use crate::coin_selection::CoinSelectionMethod;
+    use crate::errors::OfflineTransactionError::{FileNotFound, FrozenUtxo};
+    use crate::transaction::change_split::ChangeSplit;
+    use crate::transaction::ordering::OutputOrdering;
+    use crate::types::bitcoin::BitcoinTransactionData;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    const PRIVATE_KEY: &str = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+    const TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+
+    fn sample_data() -> BitcoinTransactionData {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_input(TXID, 0, Amount::from_sat(1_000_000))
+            .push_output(ADDRESS, Amount::from_sat(500_000));
+        data
+    }
+
+    fn sequences(data: BitcoinTransactionData) -> Vec<Sequence> {
+        match Platform::Bitcoin(data).generate_unsigned_transaction().unwrap() {
+            TransactionData::BitcoinTransaction { raw_transaction, .. } => {
+                raw_transaction.input.iter().map(|input| input.sequence).collect()
+            }
+        }
+    }
+
+    #[test]
+    fn test_rbf_enabled_by_default() {
+        let sequences = sequences(sample_data());
+        assert!(sequences.iter().all(|sequence| *sequence == Sequence::ENABLE_RBF_NO_LOCKTIME));
+    }
+
+    #[test]
+    fn test_rbf_disabled() {
+        let mut data = sample_data();
+        data.set_rbf(false);
+        let sequences = sequences(data);
+        assert!(sequences.iter().all(|sequence| *sequence == Sequence::MAX));
+    }
+
+    #[test]
+    fn test_lock_time_without_rbf() {
+        let mut data = sample_data();
+        data.set_rbf(false).set_lock_time(840_000);
+        match Platform::Bitcoin(data).generate_unsigned_transaction().unwrap() {
+            TransactionData::BitcoinTransaction { raw_transaction, .. } => {
+                assert_eq!(raw_transaction.lock_time, LockTime::from_consensus(840_000));
+                assert_eq!(raw_transaction.input[0].sequence, Sequence::ENABLE_LOCKTIME_NO_RBF);
+                assert!(!raw_transaction.is_explicitly_rbf());
+            }
+        }
+    }
+
+    #[test]
+    fn test_coin_selection_changeless() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        // Excess of 190 sat over 0.005 BTC output + 110 vbytes at 1 sat/vB is under the cost of change
+        data.push_utxo(TXID, 0, Amount::from_sat(500_300))
+            .push_utxo(TXID, 1, Amount::from_sat(2_000_000))
+            .push_output(ADDRESS, Amount::from_sat(500_000));
+
+        let transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        let selection = transaction.report().coin_selection.clone().unwrap();
+        assert_eq!(selection.algorithm, "branchAndBound");
+        assert!(selection.changeless);
+        match transaction {
+            TransactionData::BitcoinTransaction { raw_transaction, .. } => {
+                assert_eq!(raw_transaction.input.len(), 1);
+                assert_eq!(raw_transaction.input[0].previous_output.vout, 0);
+                assert_eq!(raw_transaction.output.len(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_coin_selection_with_change() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_utxo(TXID, 0, Amount::from_sat(300_000))
+            .push_utxo(TXID, 1, Amount::from_sat(400_000))
+            .push_output(ADDRESS, Amount::from_sat(500_000));
+
+        let transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        let selection = transaction.report().coin_selection.clone().unwrap();
+        assert_eq!(selection.algorithm, "singleRandomDraw");
+        assert_eq!(selection.selected.len(), 2);
+        match transaction {
+            TransactionData::BitcoinTransaction { raw_transaction, .. } => {
+                assert_eq!(raw_transaction.output.len(), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_coin_selection_report_in_input_order() {
+        for ordering in [OutputOrdering::Append, OutputOrdering::Bip69] {
+            let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+            // The largest UTXO is selected first, but is the last one of the pool
+            data.push_utxo(TXID, 0, Amount::from_sat(100_000))
+                .push_utxo(TXID, 1, Amount::from_sat(300_000))
+                .push_utxo(TXID, 2, Amount::from_sat(400_000))
+                .push_output(ADDRESS, Amount::from_sat(600_000))
+                .set_coin_selection(CoinSelectionMethod::LargestFirst)
+                .set_output_ordering(ordering);
+
+            let transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+            let selected = transaction.report().coin_selection.clone().unwrap().selected;
+            match transaction {
+                TransactionData::BitcoinTransaction { raw_transaction, .. } => {
+                    let input_vouts = raw_transaction.input.iter()
+                        .map(|input| input.previous_output.vout)
+                        .collect::<Vec<_>>();
+                    assert_eq!(selected.iter().map(|utxo| utxo.vout).collect::<Vec<_>>(), input_vouts);
+                    let expected = if ordering == OutputOrdering::Bip69 { vec![1, 2] } else { vec![2, 1] };
+                    assert_eq!(input_vouts, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_coin_selection_insufficient_funds() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_utxo(TXID, 0, Amount::from_sat(300_000))
+            .push_output(ADDRESS, Amount::from_sat(500_000));
+        match Platform::Bitcoin(data).generate_unsigned_transaction() {
+            Err(InsufficientFunds { shortfall, total_in, total_out, fee }) => {
+                assert_eq!(total_in, Amount::from_sat(300_000));
+                assert_eq!(total_out, Amount::from_sat(500_000));
+                assert_eq!(shortfall, total_out + fee - total_in);
+            },
+            _ => panic!("expected insufficient funds"),
+        }
+    }
+
+    #[test]
+    fn test_coin_selection_strategy_comparison() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_utxo(TXID, 0, Amount::from_sat(500_300))
+            .push_utxo(TXID, 1, Amount::from_sat(2_000_000))
+            .push_output(ADDRESS, Amount::from_sat(500_000))
+            .set_coin_selection(CoinSelectionMethod::LargestFirst);
+
+        let transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        let selection = transaction.report().coin_selection.clone().unwrap();
+        assert_eq!(selection.strategy, "largestFirst");
+        assert_eq!(selection.selected[0].vout, 1);
+        assert!(!selection.changeless);
+        assert_eq!(selection.comparison.len(), CoinSelectionMethod::ALL.len());
+
+        // The changeless Branch-and-Bound selection wastes less than creating a change output
+        let waste_of = |strategy: &str| selection.comparison.iter()
+            .find(|comparison| comparison.strategy == strategy)
+            .and_then(|comparison| comparison.waste)
+            .unwrap();
+        assert_eq!(waste_of("largestFirst"), selection.waste);
+        assert!(waste_of("auto") < waste_of("largestFirst"));
+    }
+
+    #[test]
+    fn test_change_pays_for_its_own_size() {
+        match Platform::Bitcoin(sample_data()).generate_unsigned_transaction().unwrap() {
+            TransactionData::BitcoinTransaction { raw_transaction, report, .. } => {
+                // 110 vbytes without change plus a 31 vbytes P2WPKH change output at 1 sat/vB
+                assert_eq!(raw_transaction.output[1].value.to_sat(), 1_000_000 - 500_000 - 110 - 31);
+                assert!(report.dropped_change.is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_change_within_cost_of_change_is_dropped() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        // Excess of 600 sat over the fee, under the cost of change of 31 + 68 * 10 sat
+        data.push_input(TXID, 0, Amount::from_sat(1_000_000))
+            .push_output(ADDRESS, Amount::from_sat(999_290));
+
+        match Platform::Bitcoin(data).generate_unsigned_transaction().unwrap() {
+            TransactionData::BitcoinTransaction { raw_transaction, report, .. } => {
+                assert_eq!(raw_transaction.output.len(), 1);
+                let dropped_change = report.dropped_change.unwrap();
+                assert_eq!(dropped_change.amount, 1_000_000 - 999_290 - 110);
+                assert!(dropped_change.reason.contains("cost of change of 711 sat"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_insufficient_inputs() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_input(TXID, 0, Amount::from_sat(500_000))
+            .push_output(ADDRESS, Amount::from_sat(500_000));
+        match Platform::Bitcoin(data).generate_unsigned_transaction() {
+            Err(InsufficientFunds { shortfall, total_in, total_out, fee }) => {
+                // 1 input and 1 P2WPKH output are 110 vB at 1 sat/vB
+                assert_eq!(shortfall, Amount::from_sat(110));
+                assert_eq!(total_in, Amount::from_sat(500_000));
+                assert_eq!(total_out, Amount::from_sat(500_000));
+                assert_eq!(fee, Amount::from_sat(110));
+            },
+            _ => panic!("expected insufficient funds"),
+        }
+    }
+
+    #[test]
+    fn test_overflowing_amounts_are_refused() {
+        let mut data = sample_data();
+        data.push_output(ADDRESS, Amount::MAX_MONEY);
+        assert!(matches!(Platform::Bitcoin(data).generate_unsigned_transaction(),
+                         Err(InvalidTransaction { .. })));
+
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_input(TXID, 0, Amount::MAX)
+            .push_input(TXID, 1, Amount::MAX)
+            .push_output(ADDRESS, Amount::from_sat(500_000));
+        assert!(matches!(Platform::Bitcoin(data).generate_unsigned_transaction(),
+                         Err(InvalidTransaction { .. })));
+    }
+
+    #[test]
+    fn test_change_index_is_reported() {
+        let mut data = sample_data();
+        data.push_output("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", Amount::from_sat(100_000))
+            .set_output_ordering(OutputOrdering::Bip69);
+
+        let transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        let change_indices = transaction.report().change_indices.clone();
+        match transaction {
+            TransactionData::BitcoinTransaction { raw_transaction, .. } => {
+                // The change of about 0.004 BTC sorts between the outputs of 0.001 and 0.005 BTC
+                assert_eq!(change_indices, vec![1]);
+                assert!(raw_transaction.output.windows(2).all(|pair| pair[0].value <= pair[1].value));
+            }
+        }
+    }
+
+    #[test]
+    fn test_change_split_pays_for_extra_outputs() {
+        let mut data = sample_data();
+        data.set_change_split(ChangeSplit::count(3));
+
+        let transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        assert_eq!(transaction.report().change_indices, vec![1, 2, 3]);
+        match transaction {
+            TransactionData::BitcoinTransaction { raw_transaction, input_data, .. } => {
+                let change = raw_transaction.output[1..].iter()
+                    .map(|output| output.value.to_sat())
+                    .collect::<Vec<_>>();
+                // 1 input and 4 P2WPKH outputs are 203 vB at 1 sat/vB
+                assert_eq!(change.iter().sum::<u64>(), 1_000_000 - 500_000 - 110 - 3 * 31);
+                assert!(change.iter().all(|amount| amount.abs_diff(change[0]) <= 2));
+
+                let total_input = input_data.iter().map(|(amount, _)| *amount).sum::<u64>();
+                let total_output = raw_transaction.output.iter().map(|output| output.value.to_sat()).sum::<u64>();
+                assert!(total_input - total_output >= raw_transaction.vsize() as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_amounts_with_units_are_reported() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.set_amount_unit(Denomination::Satoshi)
+            .push_input(TXID, 0, "1000000")
+            .push_output(ADDRESS, "1.5 mBTC")
+            .push_output("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", "0.001 BTC");
+
+        let transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        let amounts = transaction.report().amounts.iter()
+            .map(|amount| (amount.field.as_str(), amount.given.as_str(), amount.sat))
+            .collect::<Vec<_>>();
+        assert_eq!(amounts, vec![
+            ("outputs[0].amount", "1.5 mBTC", 150_000),
+            ("outputs[1].amount", "0.001 BTC", 100_000),
+            ("inputs[0].amount", "1000000", 1_000_000),
+        ]);
+    }
+
+    #[test]
+    fn test_number_amounts_are_warned_about() {
+        let mut data = sample_data();
+        data.push_output(ADDRESS, BitcoinAmount::Number(0.001))
+            .set_change_split(ChangeSplit::target_value(BitcoinAmount::Number(0.001)));
+        let transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        assert_eq!(transaction.report().warnings.len(), 2);
+        assert!(transaction.report().warnings[0].ends_with(": outputs[1].amount, changeSplit.targetValue"));
+
+        let transaction = Platform::Bitcoin(sample_data()).generate_unsigned_transaction().unwrap();
+        assert!(transaction.report().warnings.is_empty());
+    }
+
+    #[test]
+    fn test_f64_amounts_are_still_accepted() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_input(TXID, 0, 0.01)
+            .push_output(ADDRESS, 0.005);
+        let transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        assert!(transaction.report().warnings[0].ends_with(": inputs[0].amount, outputs[0].amount"));
+        match transaction {
+            TransactionData::BitcoinTransaction { raw_transaction, .. } => {
+                assert_eq!(raw_transaction.output[0].value, Amount::from_sat(500_000));
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_amount_names_its_field() {
+        let mut data = sample_data();
+        data.push_output(ADDRESS, "1 XBT");
+
+        let result = Platform::Bitcoin(data).generate_unsigned_transaction();
+        assert!(matches!(result, Err(ParseError { from, .. }) if from == "outputs[1].amount"));
+    }
+
+    #[test]
+    fn test_address_of_another_network_is_refused() {
+        let mut data = sample_data();
+        data.push_output("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", Amount::from_sat(100_000));
+
+        let result = Platform::Bitcoin(data).generate_unsigned_transaction();
+        assert!(matches!(result, Err(ParseError { from, .. }) if from == "outputs[1].address"));
+    }
+
+    #[test]
+    fn test_testnet_transaction() {
+        let testnet_address = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        let mut data = BitcoinTransactionData::new(testnet_address, PRIVATE_KEY);
+        data.set_network(Network::Testnet4)
+            .push_input(TXID, 0, Amount::from_sat(1_000_000));
+        data.push_output(testnet_address, Amount::from_sat(500_000));
+        assert!(Platform::Bitcoin(data).generate_unsigned_transaction().is_ok());
+
+        let mut data = BitcoinTransactionData::new(testnet_address, PRIVATE_KEY);
+        data.set_network(Network::Testnet4)
+            .push_input(TXID, 0, Amount::from_sat(1_000_000));
+        data.push_output(ADDRESS, Amount::from_sat(500_000));
+        let result = Platform::Bitcoin(data).generate_unsigned_transaction();
+        assert!(matches!(result, Err(ParseError { from, .. }) if from == "outputs[0].address"));
+    }
+
+    #[test]
+    fn test_frozen_utxos_are_not_spent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frozen.json");
+        let mut registry = FrozenRegistry::new();
+        registry.freeze(TXID, 0, Some("legal hold")).unwrap();
+        registry.save(&path).unwrap();
+
+        let mut data = sample_data();
+        data.set_frozen_utxos(path.to_str().unwrap());
+        assert!(matches!(Platform::Bitcoin(data).generate_unsigned_transaction(),
+                         Err(FrozenUtxo { .. })));
+
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_utxo(TXID, 0, Amount::from_sat(2_000_000))
+            .push_utxo(TXID, 1, Amount::from_sat(1_000_000))
+            .push_output(ADDRESS, Amount::from_sat(500_000))
+            .set_frozen_utxos(path.to_str().unwrap());
+        let transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        assert_eq!(transaction.report().frozen_utxos, vec![format!("{}:0", TXID)]);
+        match transaction {
+            TransactionData::BitcoinTransaction { raw_transaction, .. } => {
+                assert_eq!(raw_transaction.input.len(), 1);
+                assert_eq!(raw_transaction.input[0].previous_output.vout, 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_missing_frozen_registry_fails_closed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut data = sample_data();
+        data.set_frozen_utxos(dir.path().join("frozen.json").to_str().unwrap());
+        assert!(matches!(Platform::Bitcoin(data).generate_unsigned_transaction(),
+                         Err(FileNotFound { .. })));
+    }
+
+    #[test]
+    fn test_duplicate_outpoints_are_refused() {
+        let mut data = sample_data();
+        data.push_input(&TXID.to_uppercase(), 0, Amount::from_sat(1_000_000));
+        match Platform::Bitcoin(data).generate_unsigned_transaction() {
+            Err(InvalidTransaction { reason }) => {
+                assert_eq!(reason, format!("inputs[1] spends {}:0 as inputs[0] does", TXID));
+            },
+            _ => panic!("expected a duplicate outpoint"),
+        }
+
+        // Paying the same address twice is only warned about
+        let mut data = sample_data();
+        data.push_output(ADDRESS, Amount::from_sat(100_000));
+        let transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        assert_eq!(transaction.report().warnings, vec![format!("outputs[1] pays {} as outputs[0] does", ADDRESS)]);
+        match transaction {
+            TransactionData::BitcoinTransaction { raw_transaction, .. } => {
+                assert_eq!(raw_transaction.output.len(), 3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_input_sequence_signals_rbf() {
+        assert!(input_sequence(true, false).is_rbf());
+        assert!(input_sequence(true, true).enables_absolute_lock_time());
+        assert!(!input_sequence(false, false).enables_absolute_lock_time());
+    }
+}
@@ -1,9 +1,19 @@
-use bitcoin::{ScriptBuf, Transaction};
+use bitcoin::{Network, ScriptBuf, Transaction};
 use bitcoin::consensus::encode;
 use colored::Colorize;
+use crate::transaction::report::GenerationReport;
 
+pub mod change_split;
+mod consolidate_transaction;
+mod cpfp_transaction;
+pub mod decode;
 mod generate_transaction;
+pub mod ordering;
+pub mod policy;
+mod replace_transaction;
+pub mod report;
 mod sign_transaction;
+pub mod summary;
 
 
 /// Represents transaction data for different cryptocurrency platforms
@@ -16,12 +26,16 @@ mod sign_transaction;
 /// * `signed` - Boolean flag indicating if the transaction is signed
 /// * `input_data` - Vector of tuples containing amount (in satoshis) and script for each input
 /// * `private_key` - Private key used for signing the transaction
+/// * `network` - Network of the transaction, which the private key must belong to
+/// * `report` - Decisions made while generating the transaction
 pub enum TransactionData {
     BitcoinTransaction {
         raw_transaction: Transaction,
         signed: bool,
         input_data: Vec<(u64, ScriptBuf)>,
         private_key: String,
+        network: Network,
+        report: GenerationReport,
     }
 }
 
@@ -48,6 +62,17 @@ impl TransactionData {
         }
     }
 
+    /// Returns the report describing how the transaction was generated
+    ///
+    /// # Returns
+    ///
+    /// * `&GenerationReport` - The fee rate and its source used for the transaction
+    pub fn report(&self) -> &GenerationReport {
+        match self {
+            TransactionData::BitcoinTransaction { report, .. } => report,
+        }
+    }
+
     /// Returns the raw transaction as a hexadecimal string
     ///
     /// If the transaction is unsigned, displays a warning message.
@@ -61,10 +86,9 @@ impl TransactionData {
             TransactionData::BitcoinTransaction {
                 raw_transaction, signed, ..} => {
                 if !*signed {
-                    println!("{}: {}", 
-                             "WARNING".bold().yellow(), 
-                             "output transaction is unsigned, \
-                             if you want to get signed transaction, execute sign_transaction()")
+                    println!("{}: output transaction is unsigned, \
+                             if you want to get signed transaction, execute sign_transaction()",
+                             "WARNING".bold().yellow())
                 }
                 
                 hex::encode(encode::serialize(raw_transaction))
@@ -0,0 +1,229 @@
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::{ScriptBuf, Transaction};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Order of the inputs and outputs of a generated transaction
+///
+/// # Variants
+///
+/// * `Append` - Inputs and outputs in request order, with the change output appended last
+/// * `Bip69` - Inputs and outputs sorted lexicographically as specified by BIP69
+/// * `RandomChange` - Outputs in request order, with the change output at a random position
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputOrdering {
+    #[default]
+    Append,
+    Bip69,
+    RandomChange,
+}
+
+/// Orders the inputs and outputs of a transaction whose change outputs, if any, are the last ones
+///
+/// The random change positions are drawn from a generator seeded with `seed`, or with a tagged
+/// hash of the private key and the txid of the transaction before ordering, so that generating
+/// the same request twice gives the same transaction. The txid alone would not do: it can be
+/// recomputed from the broadcast transaction by moving a candidate output last.
+///
+/// # Arguments
+///
+/// * `transaction` - The unsigned transaction
+/// * `input_data` - Amounts and script_pubkeys spent by the inputs, reordered with them
+/// * `change_count` - Number of change outputs at the end of the outputs
+/// * `ordering` - The ordering to apply
+/// * `seed` - Seed of the random change positions
+/// * `private_key` - Private key of the request in WIF, the secret of the default seed
+///
+/// # Returns
+///
+/// * `Vec<usize>` - The indices of the change outputs after ordering, in ascending order
+pub(crate) fn order_transaction(transaction: &mut Transaction,
+                                input_data: &mut Vec<(u64, ScriptBuf)>,
+                                change_count: usize,
+                                ordering: OutputOrdering,
+                                seed: Option<u64>,
+                                private_key: &str) -> Vec<usize> {
+    let first_change = transaction.output.len() - change_count;
+
+    match ordering {
+        OutputOrdering::Append => (first_change..transaction.output.len()).collect(),
+        OutputOrdering::Bip69 => {
+            let mut inputs = transaction.input.drain(..)
+                .zip(input_data.drain(..))
+                .collect::<Vec<_>>();
+            // Txids are compared in the reversed byte order they are displayed in
+            inputs.sort_by_key(|(input, _)| {
+                let mut txid = input.previous_output.txid.to_byte_array();
+                txid.reverse();
+                (txid, input.previous_output.vout)
+            });
+            for (input, data) in inputs {
+                transaction.input.push(input);
+                input_data.push(data);
+            }
+
+            let mut outputs = transaction.output.drain(..)
+                .enumerate()
+                .collect::<Vec<_>>();
+            outputs.sort_by(|(_, a), (_, b)| {
+                a.value.cmp(&b.value).then_with(|| a.script_pubkey.as_bytes().cmp(b.script_pubkey.as_bytes()))
+            });
+            let change_indices = outputs.iter()
+                .enumerate()
+                .filter(|(_, (index, _))| *index >= first_change)
+                .map(|(position, _)| position)
+                .collect();
+            transaction.output = outputs.into_iter()
+                .map(|(_, output)| output)
+                .collect();
+
+            change_indices
+        },
+        OutputOrdering::RandomChange => {
+            let mut rng = match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_seed(default_seed(transaction, private_key)),
+            };
+            let changes = transaction.output.split_off(first_change);
+            let mut is_change = vec![false; first_change];
+            for change in changes {
+                let position = rng.gen_range(0..=transaction.output.len());
+                transaction.output.insert(position, change);
+                is_change.insert(position, true);
+            }
+
+            is_change.iter()
+                .enumerate()
+                .filter(|(_, is_change)| **is_change)
+                .map(|(position, _)| position)
+                .collect()
+        },
+    }
+}
+
+/// Derives the default seed of the random change positions
+///
+/// BIP340-style tagged hash of the private key and the txid, which only the signer can compute.
+fn default_seed(transaction: &Transaction, private_key: &str) -> [u8; 32] {
+    let tag = sha256::Hash::hash(b"offline_transaction/change-position");
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_byte_array());
+    engine.input(tag.as_byte_array());
+    engine.input(private_key.as_bytes());
+    engine.input(transaction.compute_txid().as_byte_array());
+
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use bitcoin::{Amount, OutPoint, Sequence, TxIn, TxOut, Txid, Witness};
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+    use super::*;
+
+    const PRIVATE_KEY: &str = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+
+    fn transaction(inputs: &[(&str, u32)], outputs: &[(u64, u8)]) -> (Transaction, Vec<(u64, ScriptBuf)>) {
+        let transaction = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: inputs.iter()
+                .map(|(txid, vout)| TxIn {
+                    previous_output: OutPoint::new(Txid::from_str(txid).unwrap(), *vout),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: outputs.iter()
+                .map(|(value, script)| TxOut {
+                    value: Amount::from_sat(*value),
+                    script_pubkey: ScriptBuf::from_bytes(vec![*script]),
+                })
+                .collect(),
+        };
+        let input_data = inputs.iter()
+            .map(|(_, vout)| (*vout as u64, ScriptBuf::new()))
+            .collect();
+        (transaction, input_data)
+    }
+
+    #[test]
+    fn test_bip69_ordering() {
+        let (mut tx, mut input_data) = transaction(
+            &[
+                ("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16", 1),
+                ("0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098", 0),
+                ("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16", 0),
+            ],
+            &[(2_000, 1), (1_000, 2), (1_000, 1)],
+        );
+
+        let change_index = order_transaction(&mut tx, &mut input_data, 1, OutputOrdering::Bip69, None, PRIVATE_KEY);
+        assert_eq!(tx.input[0].previous_output.txid.to_string(),
+                   "0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098");
+        assert_eq!(tx.input[1].previous_output.vout, 0);
+        assert_eq!(tx.input[2].previous_output.vout, 1);
+        // Amounts and scripts stay aligned with their inputs
+        assert_eq!(input_data.iter().map(|(vout, _)| *vout).collect::<Vec<_>>(), vec![0, 0, 1]);
+
+        assert_eq!(tx.output.iter().map(|output| output.value.to_sat()).collect::<Vec<_>>(), vec![1_000, 1_000, 2_000]);
+        assert_eq!(tx.output[0].script_pubkey.as_bytes(), &[1]);
+        assert_eq!(change_index, vec![0]);
+    }
+
+    #[test]
+    fn test_random_change_is_deterministic() {
+        let outputs = [(1_000, 1), (2_000, 2), (3_000, 3), (4_000, 4), (500, 9)];
+        let positions = (0..2)
+            .map(|_| {
+                let (mut tx, mut input_data) = transaction(
+                    &[("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16", 0)], &outputs);
+                let change_indices = order_transaction(
+                    &mut tx, &mut input_data, 2, OutputOrdering::RandomChange, None, PRIVATE_KEY);
+                let mut change_values = change_indices.iter()
+                    .map(|index| tx.output[*index].value.to_sat())
+                    .collect::<Vec<_>>();
+                change_values.sort();
+                assert_eq!(change_values, vec![500, 4_000]);
+                change_indices
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(positions[0], positions[1]);
+
+        let (mut tx, mut input_data) = transaction(&[], &outputs[..2]);
+        assert!(order_transaction(&mut tx, &mut input_data, 0, OutputOrdering::RandomChange, Some(7), PRIVATE_KEY).is_empty());
+        assert_eq!(tx.output[1].value.to_sat(), 2_000);
+    }
+
+    #[test]
+    fn test_random_change_depends_on_the_private_key() {
+        let outputs = [(1_000, 1), (2_000, 2), (3_000, 3), (4_000, 4), (500, 9)];
+        let change_position = |private_key: &str| {
+            let (mut tx, mut input_data) = transaction(
+                &[("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16", 0)], &outputs);
+            order_transaction(&mut tx, &mut input_data, 1, OutputOrdering::RandomChange, None, private_key)[0]
+        };
+
+        // The same transaction, which is all an observer has, gives the change different positions
+        let mut positions = (0..16)
+            .map(|key| change_position(&format!("private key {}", key)))
+            .collect::<Vec<_>>();
+        positions.sort();
+        positions.dedup();
+        assert!(positions.len() > 1);
+
+        // Seeding with the txid of the transaction with the change last does not find the position
+        let (tx, _) = transaction(&[("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16", 0)], &outputs);
+        let mut rng = StdRng::from_seed(tx.compute_txid().to_byte_array());
+        let txid_position = rng.gen_range(0..=outputs.len() - 1);
+        let (mut tx, mut input_data) = transaction(
+            &[("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16", 0)], &outputs);
+        let change_indices = order_transaction(&mut tx, &mut input_data, 1, OutputOrdering::RandomChange, None, PRIVATE_KEY);
+        assert_ne!(change_indices[0], txid_position);
+    }
+}
@@ -0,0 +1,316 @@
+use std::fmt;
+use bitcoin::{Amount, Script, ScriptBuf, Transaction, TxOut};
+use bitcoin::script::Instruction;
+use serde::Serialize;
+use crate::transaction::TransactionData;
+use crate::utils::fee_calculator::{dust_limit, estimate_weight, MAX_STANDARD_TX_WEIGHT};
+
+/// Minimum fee rate (sat/vB) of a transaction relayed by the default policy (`-minrelaytxfee`).
+pub(crate) const MIN_RELAY_FEE_RATE: u64 = 1;
+
+/// Maximum signature operation cost of a standard transaction.
+pub(crate) const MAX_STANDARD_TX_SIGOPS_COST: u64 = 16_000;
+
+/// Maximum size in bytes of a standard OP_RETURN output script (`-datacarriersize`).
+pub(crate) const MAX_OP_RETURN_RELAY: usize = 83;
+
+/// Fee rate (sat/vB) above which `sendrawtransaction` refuses a transaction (`maxfeerate` of 0.1 BTC/kvB).
+pub(crate) const MAX_FEE_RATE: u64 = 10_000;
+
+/// A relay policy rule of Bitcoin Core
+///
+/// # Variants
+///
+/// * `Dust` - Every output except OP_RETURN must be worth at least its dust limit
+/// * `MaxWeight` - The transaction must not weigh more than 400000 WU
+/// * `MaxSigops` - The signature operation cost must not exceed 16000
+/// * `OpReturnSize` - A single OP_RETURN output of at most 83 bytes is relayed
+/// * `NegativeFee` - The outputs must not exceed the inputs
+/// * `MinRelayFee` - The fee must pay at least 1 sat/vB
+/// * `NonStandardScript` - Every output script must be of a standard type
+/// * `AbsurdFee` - The fee rate must not exceed 10000 sat/vB, as it is most likely a mistake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PolicyRule {
+    Dust,
+    MaxWeight,
+    MaxSigops,
+    OpReturnSize,
+    NegativeFee,
+    MinRelayFee,
+    NonStandardScript,
+    AbsurdFee,
+}
+
+impl fmt::Display for PolicyRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PolicyRule::Dust => "dust",
+            PolicyRule::MaxWeight => "max-weight",
+            PolicyRule::MaxSigops => "max-sigops",
+            PolicyRule::OpReturnSize => "op-return-size",
+            PolicyRule::NegativeFee => "negative-fee",
+            PolicyRule::MinRelayFee => "min-relay-fee",
+            PolicyRule::NonStandardScript => "non-standard-script",
+            PolicyRule::AbsurdFee => "absurd-fee",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A relay policy rule broken by a transaction
+///
+/// # Fields
+///
+/// * `rule` - The broken rule
+/// * `reason` - Why the transaction breaks the rule
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicyViolation {
+    pub rule: PolicyRule,
+    pub reason: String,
+}
+
+impl TransactionData {
+    /// Checks the transaction against the default relay policy of Bitcoin Core
+    ///
+    /// Nodes refuse to relay a transaction breaking any of these rules even though it is valid,
+    /// so the transaction should be checked before it is signed and carried out of the air gap.
+    /// The weight of an unsigned transaction is estimated from the spent scripts.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<PolicyViolation>` - The broken rules, empty if the transaction is standard
+    pub fn check_policy(&self) -> Vec<PolicyViolation> {
+        match self {
+            TransactionData::BitcoinTransaction { raw_transaction, signed, input_data, .. } => {
+                let input_scripts = input_data.iter()
+                    .map(|(_, script_pubkey)| script_pubkey.clone())
+                    .collect::<Vec<_>>();
+                let weight = if *signed {
+                    raw_transaction.weight().to_wu()
+                } else {
+                    estimate_weight(&input_scripts, &raw_transaction.output)
+                };
+                let total_in = input_data.iter()
+                    .try_fold(Amount::ZERO, |total, (amount, _)| total.checked_add(Amount::from_sat(*amount)));
+
+                let mut violations = output_violations(&raw_transaction.output);
+                violations.extend(weight_violations(raw_transaction, &input_scripts, weight));
+                violations.extend(fee_violations(raw_transaction, total_in, weight.div_ceil(4)));
+                violations
+            }
+        }
+    }
+}
+
+/// Checks the dust limit, the script type and the OP_RETURN size of every output
+fn output_violations(outputs: &[TxOut]) -> Vec<PolicyViolation> {
+    let mut violations = Vec::new();
+    let violation = |rule, reason| PolicyViolation { rule, reason };
+
+    for (index, output) in outputs.iter().enumerate() {
+        let script = &output.script_pubkey;
+        if script.is_op_return() {
+            if script.len() > MAX_OP_RETURN_RELAY {
+                violations.push(violation(PolicyRule::OpReturnSize, format!(
+                    "output {} is an OP_RETURN of {} bytes, more than {} bytes", index, script.len(), MAX_OP_RETURN_RELAY)));
+            }
+        } else if !is_standard_script(script) {
+            violations.push(violation(PolicyRule::NonStandardScript, format!(
+                "output {} pays to a non-standard script {}", index, script.to_hex_string())));
+        } else if output.value.to_sat() < dust_limit(script) {
+            violations.push(violation(PolicyRule::Dust, format!(
+                "output {} of {} sat is under the dust limit of {} sat", index, output.value.to_sat(), dust_limit(script))));
+        }
+    }
+
+    let op_returns = outputs.iter().filter(|output| output.script_pubkey.is_op_return()).count();
+    if op_returns > 1 {
+        violations.push(violation(PolicyRule::OpReturnSize, format!(
+            "{} OP_RETURN outputs, only one is relayed", op_returns)));
+    }
+
+    violations
+}
+
+/// Checks the weight and the signature operation cost of the transaction
+fn weight_violations(transaction: &Transaction,
+                     input_scripts: &[ScriptBuf],
+                     weight: u64) -> Vec<PolicyViolation> {
+    let mut violations = Vec::new();
+
+    if weight > MAX_STANDARD_TX_WEIGHT {
+        violations.push(PolicyViolation {
+            rule: PolicyRule::MaxWeight,
+            reason: format!("{} WU, more than {} WU", weight, MAX_STANDARD_TX_WEIGHT),
+        });
+    }
+
+    let sigops_cost = sigops_cost(transaction, input_scripts);
+    if sigops_cost > MAX_STANDARD_TX_SIGOPS_COST {
+        violations.push(PolicyViolation {
+            rule: PolicyRule::MaxSigops,
+            reason: format!("signature operation cost of {}, more than {}", sigops_cost, MAX_STANDARD_TX_SIGOPS_COST),
+        });
+    }
+
+    violations
+}
+
+/// Checks the fee is not negative, and then against the minimum relay fee and the maximum fee rate
+fn fee_violations(transaction: &Transaction, total_in: Option<Amount>, vsize: u64) -> Vec<PolicyViolation> {
+    let total_out = transaction.output.iter()
+        .try_fold(Amount::ZERO, |total, output| total.checked_add(output.value));
+    let Some(fee) = total_in.zip(total_out).and_then(|(total_in, total_out)| total_in.checked_sub(total_out)) else {
+        return vec![PolicyViolation {
+            rule: PolicyRule::NegativeFee,
+            reason: "the outputs exceed the inputs".to_string(),
+        }];
+    };
+
+    let fee = fee.to_sat();
+    let min_fee = vsize.saturating_mul(MIN_RELAY_FEE_RATE);
+    if fee < min_fee {
+        vec![PolicyViolation {
+            rule: PolicyRule::MinRelayFee,
+            reason: format!("fee of {} sat for {} vB, less than {} sat/vB", fee, vsize, MIN_RELAY_FEE_RATE),
+        }]
+    } else if fee > vsize.saturating_mul(MAX_FEE_RATE) {
+        vec![PolicyViolation {
+            rule: PolicyRule::AbsurdFee,
+            reason: format!("fee of {} sat for {} vB, more than {} sat/vB", fee, vsize, MAX_FEE_RATE),
+        }]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Returns whether an output script is of a type relayed by the default policy
+///
+/// Standard scripts are P2PK, P2PKH, P2SH, bare multisig of up to 3 keys, P2WPKH, P2WSH,
+/// and witness programs of version 1 or later such as P2TR.
+fn is_standard_script(script: &Script) -> bool {
+    match script.witness_version() {
+        Some(version) if version.to_num() == 0 => script.is_p2wpkh() || script.is_p2wsh(),
+        Some(_) => true,
+        None => {
+            script.is_p2pk() || script.is_p2pkh() || script.is_p2sh()
+                || (script.is_multisig() && multisig_keys(script) <= 3)
+        }
+    }
+}
+
+/// Counts the public keys of a bare multisig script
+fn multisig_keys(script: &Script) -> usize {
+    script.instructions()
+        .filter(|instruction| matches!(instruction, Ok(Instruction::PushBytes(_))))
+        .count()
+}
+
+/// Calculates the signature operation cost of the transaction
+///
+/// Legacy signature operations of the scripts weigh 4, and a P2WPKH signature check weighs 1.
+/// P2SH inputs are assumed to be P2SH-wrapped P2WPKH as for the fee estimation.
+fn sigops_cost(transaction: &Transaction, input_scripts: &[ScriptBuf]) -> u64 {
+    let legacy = transaction.input.iter()
+        .map(|input| input.script_sig.count_sigops_legacy())
+        .chain(transaction.output.iter().map(|output| output.script_pubkey.count_sigops_legacy()))
+        .sum::<usize>() as u64;
+    let witness = input_scripts.iter()
+        .filter(|script_pubkey| script_pubkey.is_p2wpkh() || script_pubkey.is_p2sh())
+        .count() as u64;
+
+    legacy * 4 + witness
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{Network, OutPoint, Sequence, TxIn, Witness};
+    use bitcoin::absolute::LockTime;
+    use bitcoin::opcodes::all::{OP_CHECKSIG, OP_RETURN};
+    use bitcoin::script::{Builder, PushBytesBuf};
+    use bitcoin::transaction::Version;
+    use crate::transaction::report::GenerationReport;
+    use crate::utils::str_to_address::str_to_address;
+    use super::*;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn script() -> ScriptBuf {
+        str_to_address(ADDRESS, Network::Bitcoin, "address").unwrap().script_pubkey()
+    }
+
+    fn transaction(input_amount: u64, outputs: Vec<TxOut>) -> TransactionData {
+        TransactionData::BitcoinTransaction {
+            raw_transaction: Transaction {
+                version: Version::TWO,
+                lock_time: LockTime::ZERO,
+                input: vec![TxIn {
+                    previous_output: OutPoint::null(),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                }],
+                output: outputs,
+            },
+            signed: false,
+            input_data: vec![(input_amount, script())],
+            private_key: String::new(),
+            network: Network::Bitcoin,
+            report: GenerationReport::default(),
+        }
+    }
+
+    fn output(sat: u64, script_pubkey: ScriptBuf) -> TxOut {
+        TxOut { value: Amount::from_sat(sat), script_pubkey }
+    }
+
+    fn rules(transaction: &TransactionData) -> Vec<PolicyRule> {
+        transaction.check_policy().into_iter().map(|violation| violation.rule).collect()
+    }
+
+    #[test]
+    fn test_standard_transaction() {
+        // 1 input and 1 P2WPKH output are 110 vB
+        assert!(rules(&transaction(100_110, vec![output(100_000, script())])).is_empty());
+    }
+
+    #[test]
+    fn test_dust_and_fee_rules() {
+        assert_eq!(rules(&transaction(403, vec![output(293, script())])), vec![PolicyRule::Dust]);
+        assert_eq!(rules(&transaction(2_000_000, vec![output(100_000, script())])), vec![PolicyRule::AbsurdFee]);
+        assert_eq!(rules(&transaction(100_109, vec![output(100_000, script())])), vec![PolicyRule::MinRelayFee]);
+        assert_eq!(rules(&transaction(100_000, vec![output(100_001, script())])), vec![PolicyRule::NegativeFee]);
+    }
+
+    #[test]
+    fn test_output_script_rules() {
+        let op_return = |len: usize| Builder::new()
+            .push_opcode(OP_RETURN)
+            .push_slice(PushBytesBuf::try_from(vec![0u8; len]).unwrap())
+            .into_script();
+        let non_standard = Builder::new().push_opcode(OP_CHECKSIG).into_script();
+
+        let outputs = vec![output(100_000, script()), output(0, op_return(80))];
+        assert!(rules(&transaction(101_000, outputs)).is_empty());
+        let outputs = vec![output(100_000, script()), output(0, op_return(81))];
+        assert_eq!(rules(&transaction(101_000, outputs)), vec![PolicyRule::OpReturnSize]);
+        let outputs = vec![output(100_000, script()), output(0, op_return(1)), output(0, op_return(1))];
+        assert_eq!(rules(&transaction(101_000, outputs)), vec![PolicyRule::OpReturnSize]);
+        let outputs = vec![output(100_000, non_standard)];
+        assert_eq!(rules(&transaction(101_000, outputs)), vec![PolicyRule::NonStandardScript]);
+    }
+
+    #[test]
+    fn test_weight_and_sigops_rules() {
+        // 4001 P2PK outputs of 44 bytes with a signature operation cost of 4 each
+        let p2pk = Builder::new()
+            .push_slice([2u8; 33])
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let outputs = vec![output(1_000, p2pk); 4_001];
+        let violations = rules(&transaction(10_000_000, outputs));
+        assert!(violations.contains(&PolicyRule::MaxSigops));
+        assert!(violations.contains(&PolicyRule::MaxWeight));
+    }
+}
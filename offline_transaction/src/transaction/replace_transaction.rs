@@ -0,0 +1,492 @@
+use std::collections::HashSet;
+use bitcoin::{Amount, ScriptBuf, Transaction, TxOut, Witness};
+use bitcoin::absolute::LockTime;
+use crate::errors::OfflineTransactionError::{InsufficientFunds, InvalidTransaction, MissingParameter};
+use crate::errors::OfflineTransactionResult;
+use crate::frozen::FrozenRegistry;
+use crate::Platform;
+use crate::transaction::generate_transaction::input_sequence;
+use crate::transaction::report::GenerationReport;
+use crate::transaction::TransactionData;
+use crate::types::amount::checked_sum;
+use crate::types::bitcoin::BitcoinTransactionData;
+use crate::utils::fee_calculator::{dust_limit, estimate_vsize, INCREMENTAL_RELAY_FEE_RATE};
+use crate::utils::hex_to_transaction::hex_to_transaction;
+use crate::utils::str_to_address::str_to_address;
+
+impl Platform {
+    /// Generates an unsigned BIP125 replacement paying a higher fee than a stuck transaction
+    ///
+    /// The original signed transaction is taken from `originalTransaction` and the outputs it
+    /// spends have to be listed in `inputs`. The replacement keeps the inputs and outputs of the
+    /// original and takes the additional fee out of the output paying to the change address:
+    /// 1. Calculates the fee paid by the original from its prevouts
+    /// 2. Calculates the replacement fee at `feeRate`, at least the original fee plus the
+    ///    incremental relay fee for the replacement size
+    /// 3. Reduces the change output by the fee increase
+    ///
+    /// # Returns
+    ///
+    /// Returns a Result containing either:
+    /// * `TransactionData` - Contains the unsigned replacement and associated data
+    /// * `OfflineTransactionError` - If the replacement cannot be generated
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * `originalTransaction` is not specified or cannot be decoded
+    /// * A prevout of the original transaction is missing in `inputs`
+    /// * A prevout of the original transaction is frozen in the frozen UTXO registry
+    /// * The original transaction has no output paying to the change address
+    /// * The change output cannot absorb the fee increase without becoming dust
+    pub fn generate_fee_bump_transaction(&self) -> OfflineTransactionResult<TransactionData> {
+        match self {
+            Platform::Bitcoin(params) => {
+                let mut report = GenerationReport::for_transaction(params)?;
+                let original = decode_original_transaction(params, "bump the transaction fee", &mut report)?;
+                let input_data = original_prevouts(params, &original)?;
+                let total_input_amount = total_input_amount(&input_data)?;
+                let original_fee = original_fee(&original, &input_data)?;
+
+                let input_scripts = input_data.iter()
+                    .map(|(_, script_pubkey)| script_pubkey.clone())
+                    .collect::<Vec<_>>();
+                let vsize = (original.vsize() as u64)
+                    .max(estimate_vsize(&input_scripts, &original.output));
+                let fee = replacement_fee(original_fee, vsize, report.fee_rate)?;
+                let fee_increase = fee - original_fee;
+
+                let change_script = str_to_address(params.change_address(), params.network(), "changeAddress")?.script_pubkey();
+                let mut replacement = unsigned_replacement(&original, params.rbf());
+                let change_index = replacement.output.iter()
+                    .position(|output| output.script_pubkey == change_script)
+                    .ok_or_else(|| InvalidTransaction {
+                        reason: "the original transaction has no output paying to the change address \
+                                 to absorb the fee increase".to_string(),
+                    })?;
+
+                let dust = Amount::from_sat(dust_limit(&change_script));
+                let Some(change_amount) = replacement.output[change_index].value.checked_sub(fee_increase)
+                    .filter(|amount| *amount >= dust) else {
+                    // The change output has to keep at least the dust limit
+                    let total_out = checked_sum(
+                        replacement.output.iter()
+                            .enumerate()
+                            .map(|(index, output)| if index == change_index { dust } else { output.value }),
+                        "output amounts")?;
+                    let required = checked_sum([total_out, fee], "output amounts and the fee")?;
+                    return Err(InsufficientFunds {
+                        shortfall: required.checked_sub(total_input_amount).unwrap_or(Amount::ZERO),
+                        total_in: total_input_amount,
+                        total_out,
+                        fee,
+                    });
+                };
+                replacement.output[change_index].value = change_amount;
+                verify_conflicts(&original, &replacement)?;
+
+                Ok(TransactionData::BitcoinTransaction {
+                    raw_transaction: replacement,
+                    signed: false,
+                    input_data,
+                    private_key: params.private_key().to_string(),
+                    network: params.network(),
+                    report,
+                })
+            }
+        }
+    }
+
+    /// Generates an unsigned BIP125 replacement cancelling a broadcast but unconfirmed transaction
+    ///
+    /// The original signed transaction is taken from `originalTransaction` and the outputs it
+    /// spends have to be listed in `inputs`. The replacement spends every input of the original
+    /// and sends the whole amount back to the change address in a single output:
+    /// 1. Calculates the fee paid by the original from its prevouts
+    /// 2. Calculates the replacement fee at `feeRate`, at least the original fee plus the
+    ///    incremental relay fee for the replacement size
+    /// 3. Verifies the replacement conflicts with every input of the original
+    ///
+    /// # Returns
+    ///
+    /// Returns a Result containing either:
+    /// * `TransactionData` - Contains the unsigned cancel transaction and associated data
+    /// * `OfflineTransactionError` - If the cancel transaction cannot be generated
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * `originalTransaction` is not specified or cannot be decoded
+    /// * A prevout of the original transaction is missing in `inputs`
+    /// * A prevout of the original transaction is frozen in the frozen UTXO registry
+    /// * The original transaction spends the same outpoint twice
+    /// * The inputs cannot pay the replacement fee without the output becoming dust
+    pub fn generate_cancel_transaction(&self) -> OfflineTransactionResult<TransactionData> {
+        match self {
+            Platform::Bitcoin(params) => {
+                let mut report = GenerationReport::for_transaction(params)?;
+                let original = decode_original_transaction(params, "cancel the transaction", &mut report)?;
+                let input_data = original_prevouts(params, &original)?;
+                let original_fee = original_fee(&original, &input_data)?;
+
+                let change_address = str_to_address(params.change_address(), params.network(), "changeAddress")?;
+                let mut replacement = unsigned_replacement(&original, params.rbf());
+                replacement.output = vec![TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: change_address.script_pubkey(),
+                }];
+
+                let input_scripts = input_data.iter()
+                    .map(|(_, script_pubkey)| script_pubkey.clone())
+                    .collect::<Vec<_>>();
+                let vsize = estimate_vsize(&input_scripts, &replacement.output);
+                let fee = replacement_fee(original_fee, vsize, report.fee_rate)?;
+
+                let total_input_amount = total_input_amount(&input_data)?;
+                // The single output has to keep at least the dust limit
+                let dust = Amount::from_sat(dust_limit(&change_address.script_pubkey()));
+                let Some(cancel_amount) = total_input_amount.checked_sub(fee)
+                    .filter(|amount| *amount >= dust) else {
+                    let required = checked_sum([dust, fee], "output amounts and the fee")?;
+                    return Err(InsufficientFunds {
+                        shortfall: required.checked_sub(total_input_amount).unwrap_or(Amount::ZERO),
+                        total_in: total_input_amount,
+                        total_out: dust,
+                        fee,
+                    });
+                };
+                replacement.output[0].value = cancel_amount;
+                verify_conflicts(&original, &replacement)?;
+
+                Ok(TransactionData::BitcoinTransaction {
+                    raw_transaction: replacement,
+                    signed: false,
+                    input_data,
+                    private_key: params.private_key().to_string(),
+                    network: params.network(),
+                    report,
+                })
+            }
+        }
+    }
+}
+
+/// Decodes the transaction to be replaced from `originalTransaction`
+///
+/// Adds a warning to the report if the original transaction does not signal BIP125 replaceability
+/// because only nodes relaying full-RBF will accept the replacement.
+fn decode_original_transaction(params: &BitcoinTransactionData,
+                               operation: &str,
+                               report: &mut GenerationReport) -> OfflineTransactionResult<Transaction> {
+    let raw_transaction = params.original_transaction()
+        .ok_or_else(|| MissingParameter {
+            parameter: "originalTransaction".to_string(),
+            operation: operation.to_string(),
+        })?;
+
+    let original = hex_to_transaction(raw_transaction)?;
+
+    if !original.is_explicitly_rbf() {
+        report.warnings.push("original transaction does not signal BIP125 replaceability, \
+                              the replacement is only relayed by full-RBF nodes".to_string());
+    }
+
+    Ok(original)
+}
+
+/// Looks up the amount and script_pubkey of every output spent by the original transaction
+///
+/// The returned vector is ordered as the inputs of the original transaction.
+/// The replacement spends the same outputs, so none of them may be frozen.
+fn original_prevouts(params: &BitcoinTransactionData,
+                     original: &Transaction) -> OfflineTransactionResult<Vec<(u64, ScriptBuf)>> {
+    let frozen = FrozenRegistry::for_transaction(params)?;
+    original.input.iter()
+        .map(|tx_input| {
+            let outpoint = tx_input.previous_output;
+            frozen.check(&outpoint)?;
+            let (index, prevout) = params.inputs().iter()
+                .enumerate()
+                .find(|(_, input)| {
+                    input.vout() == outpoint.vout
+                        && input.txid().trim().eq_ignore_ascii_case(&outpoint.txid.to_string())
+                })
+                .ok_or_else(|| MissingParameter {
+                    parameter: format!("inputs entry for {}", outpoint),
+                    operation: "calculate the fee of the original transaction".to_string(),
+                })?;
+            let address = str_to_address(prevout.address(), params.network(), &format!("inputs[{}].address", index))?;
+            let amount = prevout.amount().resolve(&format!("inputs[{}].amount", index), params.amount_unit())?;
+
+            Ok((amount.to_sat(), address.script_pubkey()))
+        })
+        .collect()
+}
+
+/// Sums the amounts of the outputs spent by the original transaction
+fn total_input_amount(input_data: &[(u64, ScriptBuf)]) -> OfflineTransactionResult<Amount> {
+    checked_sum(input_data.iter().map(|(amount, _)| Amount::from_sat(*amount)), "input amounts")
+}
+
+/// Calculates the fee paid by the original transaction
+fn original_fee(original: &Transaction,
+                input_data: &[(u64, ScriptBuf)]) -> OfflineTransactionResult<Amount> {
+    let total_input_amount = total_input_amount(input_data)?;
+    let total_output_amount = checked_sum(original.output.iter().map(|output| output.value), "output amounts")?;
+
+    total_input_amount.checked_sub(total_output_amount)
+        .ok_or_else(|| InvalidTransaction {
+            reason: "the outputs of the original transaction exceed the amounts of its inputs".to_string(),
+        })
+}
+
+/// Calculates the fee of a replacement satisfying the BIP125 fee rules
+///
+/// The replacement pays the requested fee rate, but at least the original fee plus
+/// the incremental relay fee for its own size, which also raises the fee rate
+/// above the one of the original.
+fn replacement_fee(original_fee: Amount, vsize: u64, fee_rate: u64) -> OfflineTransactionResult<Amount> {
+    let overflow = || InvalidTransaction {
+        reason: format!("replacement fee overflows at fee rate {} sat/vB", fee_rate),
+    };
+    let target_fee = vsize.checked_mul(fee_rate).ok_or_else(overflow)?;
+    let minimum_fee = vsize.checked_mul(INCREMENTAL_RELAY_FEE_RATE)
+        .and_then(|relay_fee| relay_fee.checked_add(original_fee.to_sat()))
+        .ok_or_else(overflow)?;
+
+    Ok(Amount::from_sat(target_fee.max(minimum_fee)))
+}
+
+/// Verifies the replacement conflicts with every input of the original transaction
+///
+/// A replacement which leaves an input of the original unspent does not invalidate it, so
+/// both transactions could be confirmed. Outpoints spent twice make the transactions
+/// consensus invalid.
+fn verify_conflicts(original: &Transaction, replacement: &Transaction) -> OfflineTransactionResult<()> {
+    let original_outpoints = original.input.iter()
+        .map(|input| input.previous_output)
+        .collect::<HashSet<_>>();
+    if original_outpoints.len() != original.input.len() {
+        return Err(InvalidTransaction {
+            reason: "the original transaction spends the same outpoint more than once".to_string(),
+        });
+    }
+
+    let replacement_outpoints = replacement.input.iter()
+        .map(|input| input.previous_output)
+        .collect::<HashSet<_>>();
+    if let Some(outpoint) = original_outpoints.difference(&replacement_outpoints).next() {
+        return Err(InvalidTransaction {
+            reason: format!("the replacement does not spend {} of the original transaction", outpoint),
+        });
+    }
+
+    Ok(())
+}
+
+/// Copies the original transaction without its signatures
+fn unsigned_replacement(original: &Transaction, rbf: bool) -> Transaction {
+    let mut replacement = original.clone();
+    let sequence = input_sequence(rbf, replacement.lock_time != LockTime::ZERO);
+    for input in replacement.input.iter_mut() {
+        input.script_sig = ScriptBuf::new();
+        input.witness = Witness::new();
+        input.sequence = sequence;
+    }
+
+    replacement
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{Network, Sequence};
+    use bitcoin::consensus::encode;
+    use crate::errors::OfflineTransactionError::FrozenUtxo;
+    use super::*;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    const RECIPIENT: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+    const PRIVATE_KEY: &str = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+    const TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+
+    fn signed_original(output_amount: Amount) -> (String, Transaction) {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_input(TXID, 0, Amount::from_sat(1_000_000))
+            .push_output(RECIPIENT, output_amount);
+        let mut transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        let raw_transaction = transaction.sign_transaction().unwrap().get_raw_transaction();
+
+        let original = encode::deserialize_hex(&raw_transaction).unwrap();
+        (raw_transaction, original)
+    }
+
+    fn bump_data(raw_transaction: &str, fee_rate: u64) -> BitcoinTransactionData {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_input(TXID, 0, Amount::from_sat(1_000_000))
+            .set_fee_rate(fee_rate)
+            .set_original_transaction(raw_transaction);
+        data
+    }
+
+    #[test]
+    fn test_fee_bump_reduces_change() {
+        let (raw_transaction, original) = signed_original(Amount::from_sat(500_000));
+        let platform = Platform::Bitcoin(bump_data(&raw_transaction, 10));
+        let replacement = platform.generate_fee_bump_transaction().unwrap();
+
+        match replacement {
+            TransactionData::BitcoinTransaction { raw_transaction, input_data, .. } => {
+                let paid_fee = original_fee(&original, &input_data).unwrap().to_sat();
+                let fee = original_fee(&raw_transaction, &input_data).unwrap().to_sat();
+
+                assert_eq!(raw_transaction.input[0].previous_output, original.input[0].previous_output);
+                assert_eq!(raw_transaction.input[0].sequence, Sequence::ENABLE_RBF_NO_LOCKTIME);
+                assert!(raw_transaction.input[0].witness.is_empty());
+                assert_eq!(raw_transaction.output[0], original.output[0]);
+                assert!(fee >= original.vsize() as u64 * 10);
+                assert!(fee >= paid_fee + original.vsize() as u64 * INCREMENTAL_RELAY_FEE_RATE);
+                assert_eq!(raw_transaction.output[1].value.to_sat(),
+                           original.output[1].value.to_sat() - (fee - paid_fee));
+            }
+        }
+    }
+
+    #[test]
+    fn test_fee_bump_pays_incremental_relay_fee() {
+        let (raw_transaction, original) = signed_original(Amount::from_sat(500_000));
+        // Same fee rate as the original still has to raise the fee
+        let platform = Platform::Bitcoin(bump_data(&raw_transaction, 1));
+        match platform.generate_fee_bump_transaction().unwrap() {
+            TransactionData::BitcoinTransaction { raw_transaction, input_data, .. } => {
+                let paid_fee = original_fee(&original, &input_data).unwrap().to_sat();
+                let fee = original_fee(&raw_transaction, &input_data).unwrap().to_sat();
+                assert!(fee > paid_fee);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fee_bump_warns_about_non_replaceable_original() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_input(TXID, 0, Amount::from_sat(1_000_000))
+            .push_output(RECIPIENT, Amount::from_sat(500_000))
+            .set_rbf(false);
+        let mut transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        let raw_transaction = transaction.sign_transaction().unwrap().get_raw_transaction();
+
+        let replacement = Platform::Bitcoin(bump_data(&raw_transaction, 10)).generate_fee_bump_transaction().unwrap();
+        assert_eq!(replacement.report().warnings.len(), 1);
+        assert!(replacement.report().warnings[0].contains("full-RBF"));
+
+        let (raw_transaction, _) = signed_original(Amount::from_sat(500_000));
+        let replacement = Platform::Bitcoin(bump_data(&raw_transaction, 10)).generate_fee_bump_transaction().unwrap();
+        assert!(replacement.report().warnings.is_empty());
+    }
+
+    #[test]
+    fn test_fee_bump_change_cannot_absorb() {
+        let (raw_transaction, original) = signed_original(Amount::from_sat(998_000));
+        let platform = Platform::Bitcoin(bump_data(&raw_transaction, 50));
+        match platform.generate_fee_bump_transaction() {
+            Err(InsufficientFunds { shortfall, total_in, total_out, fee }) => {
+                let change_script = str_to_address(ADDRESS, Network::Bitcoin, "address").unwrap().script_pubkey();
+                assert_eq!(total_in, Amount::from_sat(1_000_000));
+                // The change output is counted at the dust limit
+                assert_eq!(total_out, original.output[0].value + Amount::from_sat(dust_limit(&change_script)));
+                assert_eq!(shortfall, total_out + fee - total_in);
+            }
+            _ => panic!("expected insufficient funds"),
+        }
+    }
+
+    #[test]
+    fn test_cancel_inputs_cannot_pay_fee() {
+        let (raw_transaction, _) = signed_original(Amount::from_sat(500_000));
+        let platform = Platform::Bitcoin(bump_data(&raw_transaction, 10_000));
+        match platform.generate_cancel_transaction() {
+            Err(InsufficientFunds { shortfall, total_in, total_out, fee }) => {
+                assert_eq!(total_in, Amount::from_sat(1_000_000));
+                assert_eq!(shortfall, total_out + fee - total_in);
+            }
+            _ => panic!("expected insufficient funds"),
+        }
+    }
+
+    #[test]
+    fn test_fee_beyond_money_supply() {
+        // The fee fits in a u64 but the outputs and the fee exceed the money supply
+        let (raw_transaction, _) = signed_original(Amount::from_sat(500_000));
+        let platform = Platform::Bitcoin(bump_data(&raw_transaction, 100_000_000_000_000));
+        assert!(matches!(platform.generate_fee_bump_transaction(), Err(InvalidTransaction { .. })));
+        assert!(matches!(platform.generate_cancel_transaction(), Err(InvalidTransaction { .. })));
+    }
+
+    #[test]
+    fn test_fee_bump_missing_prevout() {
+        let (raw_transaction, _) = signed_original(Amount::from_sat(500_000));
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.set_original_transaction(&raw_transaction);
+        let platform = Platform::Bitcoin(data);
+        assert!(matches!(platform.generate_fee_bump_transaction(), Err(MissingParameter { .. })));
+    }
+
+    #[test]
+    fn test_cancel_frozen_prevout() {
+        let (raw_transaction, _) = signed_original(Amount::from_sat(500_000));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frozen.json");
+        let mut registry = FrozenRegistry::new();
+        registry.freeze(TXID, 0, Some("reserved for a payout")).unwrap();
+        registry.save(&path).unwrap();
+
+        let mut data = bump_data(&raw_transaction, 10);
+        data.set_frozen_utxos(path.to_str().unwrap());
+        let platform = Platform::Bitcoin(data);
+        assert!(matches!(platform.generate_cancel_transaction(), Err(FrozenUtxo { .. })));
+    }
+
+    #[test]
+    fn test_cancel_sends_everything_to_change() {
+        let (raw_transaction, original) = signed_original(Amount::from_sat(500_000));
+        let platform = Platform::Bitcoin(bump_data(&raw_transaction, 5));
+
+        match platform.generate_cancel_transaction().unwrap() {
+            TransactionData::BitcoinTransaction { raw_transaction, input_data, .. } => {
+                let paid_fee = original_fee(&original, &input_data).unwrap().to_sat();
+                let fee = original_fee(&raw_transaction, &input_data).unwrap().to_sat();
+                let change_script = str_to_address(ADDRESS, Network::Bitcoin, "address").unwrap().script_pubkey();
+
+                assert_eq!(raw_transaction.input.len(), original.input.len());
+                assert_eq!(raw_transaction.output.len(), 1);
+                assert_eq!(raw_transaction.output[0].script_pubkey, change_script);
+                assert!(fee >= paid_fee + estimate_vsize(&[change_script], &raw_transaction.output));
+                assert!(verify_conflicts(&original, &raw_transaction).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_conflicts_missing_input() {
+        let (_, original) = signed_original(Amount::from_sat(500_000));
+        let mut replacement = original.clone();
+        replacement.input.clear();
+        assert!(matches!(verify_conflicts(&original, &replacement), Err(InvalidTransaction { .. })));
+    }
+
+    #[test]
+    fn test_verify_conflicts_duplicate_outpoint() {
+        let (_, mut original) = signed_original(Amount::from_sat(500_000));
+        original.input.push(original.input[0].clone());
+        let replacement = original.clone();
+        assert!(matches!(verify_conflicts(&original, &replacement), Err(InvalidTransaction { .. })));
+    }
+
+    #[test]
+    fn test_fee_bump_missing_original() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, PRIVATE_KEY);
+        data.push_input(TXID, 0, Amount::from_sat(1_000_000));
+        let platform = Platform::Bitcoin(data);
+        assert!(matches!(platform.generate_fee_bump_transaction(), Err(MissingParameter { .. })));
+    }
+}
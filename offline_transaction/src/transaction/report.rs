@@ -0,0 +1,199 @@
+use serde::Serialize;
+use crate::errors::OfflineTransactionResult;
+use crate::types::bitcoin::BitcoinTransactionData;
+use crate::utils::fee_estimator::resolve_fee_rate;
+
+
+/// Describes the decisions made while generating a transaction
+///
+/// The report is created together with the unsigned transaction so that operators
+/// can review how the transaction was built before signing and broadcasting it.
+///
+/// # Fields
+///
+/// * `fee_rate` - Fee rate in satoshis per virtual byte used for the fee calculation
+/// * `fee_snapshot` - The fee snapshot used to estimate `fee_rate`, if the fee rate was estimated
+/// * `coin_selection` - The automatic coin selection result, if the inputs were selected from the UTXO pool
+/// * `consolidation` - The part of the consolidation, if the transaction consolidates UTXOs
+/// * `dropped_change` - The amount added to the fee instead of a change output, if any
+/// * `change_indices` - Indices of the change outputs in the transaction
+/// * `frozen_utxos` - UTXOs of the pool left out because they are frozen, as `txid:vout`
+/// * `amounts` - The amounts of the spent inputs and the outputs as given and as interpreted
+/// * `warnings` - Suspicious but valid parts of the request, such as two outputs paying the same address
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationReport {
+    pub fee_rate: u64,
+    pub fee_snapshot: Option<FeeSnapshotReport>,
+    pub coin_selection: Option<CoinSelectionReport>,
+    pub consolidation: Option<ConsolidationReport>,
+    pub dropped_change: Option<DroppedChangeReport>,
+    pub change_indices: Vec<usize>,
+    pub frozen_utxos: Vec<String>,
+    pub amounts: Vec<InterpretedAmount>,
+    pub warnings: Vec<String>,
+}
+
+/// Describes how an amount of the request was interpreted
+///
+/// # Fields
+///
+/// * `field` - Path of the amount in the input JSON, such as `outputs[0].amount`
+/// * `given` - The amount as given, such as `1.5 mBTC`
+/// * `sat` - The interpreted amount in satoshis
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterpretedAmount {
+    pub field: String,
+    pub given: String,
+    pub sat: u64,
+}
+
+/// Describes the fee snapshot used to estimate the fee rate
+///
+/// # Fields
+///
+/// * `path` - Path of the snapshot file
+/// * `timestamp` - UNIX timestamp at which the snapshot was taken
+/// * `age` - Age of the snapshot in seconds at generation time
+/// * `fee_target` - Requested confirmation target in blocks
+/// * `source` - Part of the snapshot the fee rate was chosen from (`estimates` or `feeHistogram`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeSnapshotReport {
+    pub path: String,
+    pub timestamp: u64,
+    pub age: u64,
+    pub fee_target: u16,
+    pub source: String,
+}
+
+/// Describes which UTXOs were selected from the pool and why
+///
+/// # Fields
+///
+/// * `strategy` - The coin selection strategy used
+/// * `algorithm` - The algorithm which found the selection
+/// * `reason` - Why the algorithm was chosen and what the selection achieves
+/// * `changeless` - Whether the selection funds the transaction without a change output
+/// * `waste` - Cost of the selection compared to spending it at the long term fee rate, in satoshis
+/// * `selected` - The selected UTXOs in input order
+/// * `clusters` - Clusters of the selected UTXOs (the address for UTXOs without cluster), more than
+///   one if the selection links them on-chain
+/// * `comparison` - The waste of every built-in strategy for the same pool and outputs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinSelectionReport {
+    pub strategy: String,
+    pub algorithm: String,
+    pub reason: String,
+    pub changeless: bool,
+    pub waste: i64,
+    pub selected: Vec<SelectedUtxo>,
+    pub clusters: Vec<String>,
+    pub comparison: Vec<StrategyWaste>,
+}
+
+/// Waste of a built-in coin selection strategy
+///
+/// The optional fields are `None` if the strategy cannot fund the transaction.
+///
+/// # Fields
+///
+/// * `strategy` - Name of the strategy
+/// * `inputs` - Number of selected UTXOs
+/// * `changeless` - Whether the selection funds the transaction without a change output
+/// * `waste` - Waste of the selection in satoshis
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyWaste {
+    pub strategy: String,
+    pub inputs: Option<usize>,
+    pub changeless: Option<bool>,
+    pub waste: Option<i64>,
+}
+
+/// A UTXO of the pool
+///
+/// # Fields
+///
+/// * `txid` - The transaction ID of the UTXO
+/// * `vout` - The output index of the UTXO
+/// * `amount` - The amount of the UTXO in satoshis
+/// * `effective_value` - The amount minus the fee to spend the UTXO at the fee rate
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectedUtxo {
+    pub txid: String,
+    pub vout: u32,
+    pub amount: u64,
+    pub effective_value: i64,
+}
+
+/// Describes a change output which was not created
+///
+/// # Fields
+///
+/// * `amount` - Amount in satoshis added to the fee instead of the change output
+/// * `reason` - Why the change output was not created
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DroppedChangeReport {
+    pub amount: u64,
+    pub reason: String,
+}
+
+/// Describes a consolidation transaction
+///
+/// # Fields
+///
+/// * `part` - Index of the transaction among the consolidation transactions, starting from 1
+/// * `parts` - Number of consolidation transactions
+/// * `inputs` - Number of UTXOs consolidated by the transaction
+/// * `weight` - Estimated weight of the signed transaction
+/// * `uneconomical` - UTXOs of the pool left out because spending them costs more than their amount
+///   at the fee rate (the same for every part)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsolidationReport {
+    pub part: usize,
+    pub parts: usize,
+    pub inputs: usize,
+    pub weight: u64,
+    pub uneconomical: Vec<SelectedUtxo>,
+}
+
+impl GenerationReport {
+    /// Creates a report for a transaction generated at the given fee rate
+    pub(crate) fn new(fee_rate: u64, fee_snapshot: Option<FeeSnapshotReport>) -> Self {
+        Self {
+            fee_rate,
+            fee_snapshot,
+            ..Default::default()
+        }
+    }
+
+    /// Creates the report of a transaction generated from the transaction data
+    ///
+    /// The fee rate is resolved from the transaction data, and the amounts given as JSON
+    /// numbers are warned about.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The transaction data
+    ///
+    /// # Returns
+    ///
+    /// * `OfflineTransactionResult<GenerationReport>` - The report, or an error if the fee rate cannot be resolved
+    pub(crate) fn for_transaction(params: &BitcoinTransactionData) -> OfflineTransactionResult<Self> {
+        let mut report = resolve_fee_rate(params)?;
+        let number_amounts = params.number_amounts();
+        if !number_amounts.is_empty() {
+            report.warnings.push(format!(
+                "amounts given as JSON numbers are deprecated, give them as strings such as \"0.29\" or \"0.29 BTC\": {}",
+                number_amounts.join(", ")));
+        }
+
+        Ok(report)
+    }
+}
@@ -1,18 +1,19 @@
-use bitcoin::{Amount, EcdsaSighashType, PrivateKey, PublicKey, ScriptBuf, Witness};
+use bitcoin::{Amount, EcdsaSighashType, PublicKey, ScriptBuf, Witness};
 use bitcoin::hashes::Hash;
 use bitcoin::script::PushBytesBuf;
 use bitcoin::secp256k1::{Message, Secp256k1};
 use bitcoin::sighash::SighashCache;
-use crate::errors::OfflineTransactionError::{HasherError, ParseError, UnsupportedError};
+use crate::errors::OfflineTransactionError::{HasherError, InvalidTransaction, ParseError, UnsupportedError};
 use crate::errors::OfflineTransactionResult;
 use crate::transaction::TransactionData;
+use crate::utils::wif_to_private_key::wif_to_private_key;
 
 impl TransactionData {
     /// Signs the transaction using the appropriate cryptographic algorithm for the platform
     ///
     /// This method handles the digital signature process for transaction inputs by:
     /// 1. Initializing the cryptographic context
-    /// 2. Converting the private key from its string format
+    /// 2. Converting the private key from its string format and checking it belongs to the network
     /// 3. Deriving the corresponding public key
     /// 4. For each transaction input:
     ///    - Generates the signature hash based on input type
@@ -22,9 +23,11 @@ impl TransactionData {
     ///
     /// # Returns
     ///
-    /// * `OfflineTransactionResult<&mut Self>` - Ok(&mut TransactionData) if signing succeeds, 
-    /// or an error if:
-    ///   - Private key format is invalid
+    /// * `OfflineTransactionResult<&mut Self>` - Ok(&mut TransactionData) if signing succeeds,
+    ///   or an error if:
+    ///   - Private key format is invalid, or the key belongs to another network
+    ///   - An input is P2WPKH but the private key is uncompressed
+    ///   - An input spends the script of another public key than the one of the private key
     ///   - Signature generation fails
     ///   - Input script type is unsupported
     pub fn sign_transaction(&mut self) -> OfflineTransactionResult<&mut Self> {
@@ -35,18 +38,16 @@ impl TransactionData {
                 raw_transaction,
                 input_data, 
                 private_key,
+                network,
                 ..
             } => {
                 let hash_type = EcdsaSighashType::All;
-                let private_key = PrivateKey::from_wif(private_key)
-                    .map_err(|e| ParseError {
-                        from: "WIF string".to_string(),
-                        to: "PrivateKey".to_string(),
-                        reason: e.to_string()
-                    })?;
+                let private_key = wif_to_private_key(private_key, *network)?;
                 let public_key = PublicKey::from_private_key(&secp, &private_key);
-                
-                
+
+                // Every input is checked before signing, so a failure leaves the transaction untouched
+                check_input_scripts(input_data, &public_key)?;
+
                 for (idx, (amount, script_pubkey)) in input_data.iter().enumerate() {
                     if script_pubkey.is_p2pkh() {
                         let sighash = SighashCache::new(&mut *raw_transaction)
@@ -73,7 +74,8 @@ impl TransactionData {
 
                         raw_transaction.input[idx].script_sig = script_sig;
                     }
-                    else if script_pubkey.is_p2wpkh() {
+                    else {
+                        // P2WPKH, the only other script type passing check_input_scripts
                         let sighash = SighashCache::new(&mut *raw_transaction)
                             .p2wpkh_signature_hash(idx, script_pubkey, Amount::from_sat(*amount), hash_type)
                             .map_err(|e| HasherError(e.to_string()))?;
@@ -92,13 +94,6 @@ impl TransactionData {
                         input.witness = witness;
                         input.script_sig = ScriptBuf::new();
                     }
-                    else {
-                        return Err(UnsupportedError {
-                            component: "script_pubey type".to_string(),
-                            input: "unknown".to_string(),
-                            expected: "p2pkh, p2wpkh".to_string(),
-                        });
-                    }
                 }
             }
         }
@@ -106,4 +101,122 @@ impl TransactionData {
         self.signed();
         Ok(self)
     }
-}
\ No newline at end of file
+}
+
+/// Checks every input spends a script the public key can sign
+///
+/// A signature made with the key of another script, such as the uncompressed key of a P2PKH
+/// address derived from the compressed key, looks valid but fails consensus.
+fn check_input_scripts(input_data: &[(u64, ScriptBuf)], public_key: &PublicKey) -> OfflineTransactionResult<()> {
+    for (idx, (_, script_pubkey)) in input_data.iter().enumerate() {
+        if script_pubkey.is_p2pkh() {
+            if *script_pubkey != ScriptBuf::new_p2pkh(&public_key.pubkey_hash()) {
+                return Err(InvalidTransaction {
+                    reason: format!("input {} is P2PKH of another public key than the one of the private key", idx),
+                });
+            }
+        }
+        else if script_pubkey.is_p2wpkh() {
+            // Segwit v0 only commits to compressed public keys
+            let wpubkey_hash = public_key.wpubkey_hash()
+                .map_err(|_| InvalidTransaction {
+                    reason: format!("input {} is P2WPKH, which cannot be signed with an uncompressed private key", idx),
+                })?;
+            if *script_pubkey != ScriptBuf::new_p2wpkh(&wpubkey_hash) {
+                return Err(InvalidTransaction {
+                    reason: format!("input {} is P2WPKH of another public key than the one of the private key", idx),
+                });
+            }
+        }
+        else {
+            return Err(UnsupportedError {
+                component: "script_pubey type".to_string(),
+                input: "unknown".to_string(),
+                expected: "p2pkh, p2wpkh".to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use bitcoin::Network;
+    use crate::Platform;
+    use crate::types::bitcoin::BitcoinTransactionData;
+    use super::*;
+
+    const P2WPKH_ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    const OTHER_P2WPKH_ADDRESS: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+    // The P2PKH address of the secret key 1, compressed
+    const COMPRESSED_P2PKH_ADDRESS: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+    const TESTNET_ADDRESS: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+    // The same secret key 1, uncompressed
+    const UNCOMPRESSED_P2PKH_ADDRESS: &str = "1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm";
+    const PRIVATE_KEY: &str = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+    const UNCOMPRESSED_PRIVATE_KEY: &str = "5HpHagT65TZzG1PH3CSu63k8DbpvD8s5ip4nEB3kEsreAnchuDf";
+    const TESTNET_PRIVATE_KEY: &str = "cMahea7zqjxrtgAbB7LSGbcQUr1uX1ojuat9jZodMN87JcbXMTcA";
+    const COMPRESSED_PUBLIC_KEY: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+
+    fn sign(data: BitcoinTransactionData) -> OfflineTransactionResult<bool> {
+        let mut transaction = Platform::Bitcoin(data).generate_unsigned_transaction()?;
+        transaction.sign_transaction()?;
+
+        Ok(transaction.is_signed())
+    }
+
+    fn data(address: &str, private_key: &str, network: Network) -> BitcoinTransactionData {
+        let mut data = BitcoinTransactionData::new(address, private_key);
+        data.set_network(network)
+            .push_input(TXID, 0, Amount::from_sat(1_000_000))
+            .push_output(address, Amount::from_sat(500_000));
+        data
+    }
+
+    #[test]
+    fn test_sign_with_key_of_the_network() {
+        assert!(sign(data(P2WPKH_ADDRESS, PRIVATE_KEY, Network::Bitcoin)).unwrap());
+        assert!(sign(data(TESTNET_ADDRESS, TESTNET_PRIVATE_KEY, Network::Testnet4)).unwrap());
+    }
+
+    #[test]
+    fn test_sign_with_key_of_another_network() {
+        assert!(matches!(sign(data(TESTNET_ADDRESS, PRIVATE_KEY, Network::Testnet)), Err(InvalidTransaction { .. })));
+        assert!(matches!(sign(data(P2WPKH_ADDRESS, TESTNET_PRIVATE_KEY, Network::Bitcoin)), Err(InvalidTransaction { .. })));
+    }
+
+    #[test]
+    fn test_sign_with_uncompressed_key() {
+        assert!(sign(data(UNCOMPRESSED_P2PKH_ADDRESS, UNCOMPRESSED_PRIVATE_KEY, Network::Bitcoin)).unwrap());
+        assert!(matches!(sign(data(P2WPKH_ADDRESS, UNCOMPRESSED_PRIVATE_KEY, Network::Bitcoin)), Err(InvalidTransaction { .. })));
+    }
+
+    #[test]
+    fn test_sign_with_key_of_another_script() {
+        // The address of the compressed key cannot be signed with the uncompressed key, and conversely
+        let error = sign(data(COMPRESSED_P2PKH_ADDRESS, UNCOMPRESSED_PRIVATE_KEY, Network::Bitcoin)).unwrap_err();
+        assert!(matches!(&error, InvalidTransaction { reason } if reason.starts_with("input 0 is P2PKH")));
+        assert!(matches!(sign(data(UNCOMPRESSED_P2PKH_ADDRESS, PRIVATE_KEY, Network::Bitcoin)), Err(InvalidTransaction { .. })));
+        assert!(sign(data(COMPRESSED_P2PKH_ADDRESS, PRIVATE_KEY, Network::Bitcoin)).unwrap());
+        let error = sign(data(OTHER_P2WPKH_ADDRESS, PRIVATE_KEY, Network::Bitcoin)).unwrap_err();
+        assert!(matches!(&error, InvalidTransaction { reason } if reason.starts_with("input 0 is P2WPKH of another")));
+    }
+
+    #[test]
+    fn test_sign_mixed_inputs_with_uncompressed_key() {
+        let mut data = data(UNCOMPRESSED_P2PKH_ADDRESS, UNCOMPRESSED_PRIVATE_KEY, Network::Bitcoin);
+        data.push_input(TXID, 1, Amount::from_sat(1_000_000));
+        let mut transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        let TransactionData::BitcoinTransaction { input_data, .. } = &mut transaction;
+        input_data[1].1 = ScriptBuf::new_p2wpkh(&PublicKey::from_str(COMPRESSED_PUBLIC_KEY).unwrap().wpubkey_hash().unwrap());
+        let unsigned = transaction.get_raw_transaction();
+
+        // The P2PKH input is not signed before the P2WPKH input is refused
+        assert!(matches!(transaction.sign_transaction(), Err(InvalidTransaction { .. })));
+        assert_eq!(transaction.get_raw_transaction(), unsigned);
+        assert!(!transaction.is_signed());
+    }
+}
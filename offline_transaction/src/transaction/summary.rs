@@ -0,0 +1,152 @@
+use bitcoin::consensus::encode;
+use serde::Serialize;
+use bitcoin::Amount;
+use crate::errors::OfflineTransactionError::InsufficientFunds;
+use crate::errors::OfflineTransactionResult;
+use crate::transaction::TransactionData;
+use crate::types::amount::checked_sum;
+use crate::utils::fee_calculator::estimate_weight;
+
+/// Summarizes a generated transaction
+///
+/// The txid does not depend on the signatures, but the wtxid, the size and the weight do:
+/// for an unsigned transaction the weight and the virtual size are estimated from the spent
+/// scripts, and `size` is the size of the transaction without its signatures.
+///
+/// # Fields
+///
+/// * `txid` - Transaction ID
+/// * `wtxid` - Witness transaction ID
+/// * `size` - Serialized size in bytes
+/// * `vsize` - Virtual size in vbytes
+/// * `weight` - Weight in weight units
+/// * `estimated` - Whether `vsize` and `weight` are estimated because the transaction is unsigned
+/// * `total_in` - Sum of the spent inputs in satoshis
+/// * `total_out` - Sum of the outputs in satoshis
+/// * `fee` - Fee paid in satoshis
+/// * `fee_rate` - Effective fee rate in satoshis per virtual byte
+/// * `change_indices` - Indices of the change outputs in the transaction
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionSummary {
+    pub txid: String,
+    pub wtxid: String,
+    pub size: usize,
+    pub vsize: u64,
+    pub weight: u64,
+    pub estimated: bool,
+    pub total_in: u64,
+    pub total_out: u64,
+    pub fee: u64,
+    pub fee_rate: f64,
+    pub change_indices: Vec<usize>,
+}
+
+impl TransactionData {
+    /// Summarizes the transaction: its ids, sizes, totals, fee and effective fee rate
+    ///
+    /// # Returns
+    ///
+    /// Returns a Result containing either:
+    /// * `TransactionSummary` - The summary of the transaction
+    /// * `OfflineTransactionError` - If the total of the inputs or of the outputs exceeds 21 million BTC,
+    ///   or if the outputs spend more than the inputs
+    pub fn summary(&self) -> OfflineTransactionResult<TransactionSummary> {
+        match self {
+            TransactionData::BitcoinTransaction { raw_transaction, signed, input_data, report, .. } => {
+                let weight = if *signed {
+                    raw_transaction.weight().to_wu()
+                } else {
+                    let input_scripts = input_data.iter()
+                        .map(|(_, script_pubkey)| script_pubkey.clone())
+                        .collect::<Vec<_>>();
+                    estimate_weight(&input_scripts, &raw_transaction.output)
+                };
+                let vsize = weight.div_ceil(4);
+                let total_in = checked_sum(input_data.iter().map(|(amount, _)| Amount::from_sat(*amount)), "input amounts")?;
+                let total_out = checked_sum(raw_transaction.output.iter().map(|output| output.value), "output amounts")?;
+                // A negative fee makes the transaction invalid, it is not summarized as a zero fee
+                let fee = total_in.checked_sub(total_out)
+                    .ok_or_else(|| InsufficientFunds {
+                        shortfall: total_out - total_in,
+                        total_in,
+                        total_out,
+                        fee: Amount::ZERO,
+                    })?
+                    .to_sat();
+
+                Ok(TransactionSummary {
+                    txid: raw_transaction.compute_txid().to_string(),
+                    wtxid: raw_transaction.compute_wtxid().to_string(),
+                    size: encode::serialize(raw_transaction).len(),
+                    vsize,
+                    weight,
+                    estimated: !*signed,
+                    total_in: total_in.to_sat(),
+                    total_out: total_out.to_sat(),
+                    fee,
+                    fee_rate: fee as f64 / vsize as f64,
+                    change_indices: report.change_indices.clone(),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::Amount;
+    use crate::errors::OfflineTransactionError::InsufficientFunds;
+    use crate::Platform;
+    use crate::transaction::TransactionData;
+    use crate::types::bitcoin::BitcoinTransactionData;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    #[test]
+    fn test_summary_before_and_after_signing() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn");
+        data.push_input("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16", 1, "0.01")
+            .push_output("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", "0.005")
+            .set_fee_rate(2);
+        let mut transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+
+        let unsigned = transaction.summary().unwrap();
+        assert!(unsigned.estimated);
+        assert_eq!(unsigned.total_in, 1_000_000);
+        assert_eq!(unsigned.fee, unsigned.total_in - unsigned.total_out);
+        assert_eq!(unsigned.change_indices, vec![1]);
+        assert_eq!(unsigned.fee_rate, unsigned.fee as f64 / unsigned.vsize as f64);
+
+        transaction.sign_transaction().unwrap();
+        let signed = transaction.summary().unwrap();
+        assert!(!signed.estimated);
+        assert_eq!(signed.txid, unsigned.txid);
+        assert_ne!(signed.wtxid, signed.txid);
+        assert!(signed.size > unsigned.size);
+        assert_eq!(signed.fee, unsigned.fee);
+        assert_eq!(signed.vsize, transaction.decode().vsize as u64);
+        // The estimated weight is an upper bound of the signed weight
+        assert!(signed.weight <= unsigned.weight);
+        assert!(signed.fee_rate >= 2.0);
+    }
+
+    #[test]
+    fn test_summary_refuses_negative_fee() {
+        let mut data = BitcoinTransactionData::new(ADDRESS, "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn");
+        data.push_input("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16", 1, "0.01")
+            .push_output("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", "0.005");
+        let mut transaction = Platform::Bitcoin(data).generate_unsigned_transaction().unwrap();
+        let TransactionData::BitcoinTransaction { input_data, .. } = &mut transaction;
+        input_data[0].0 = 400_000;
+
+        match transaction.summary() {
+            Err(InsufficientFunds { shortfall, total_in, total_out, fee }) => {
+                assert_eq!(total_in, Amount::from_sat(400_000));
+                assert_eq!(shortfall, total_out - total_in);
+                assert_eq!(fee, Amount::ZERO);
+            }
+            _ => panic!("expected insufficient funds"),
+        }
+    }
+}
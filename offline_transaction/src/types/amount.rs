@@ -0,0 +1,209 @@
+use std::fmt;
+use std::str::FromStr;
+use bitcoin::{Amount, Denomination};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::errors::OfflineTransactionError::{InvalidTransaction, ParseError};
+use crate::errors::OfflineTransactionResult;
+
+/// An amount as given in the input JSON or to the builder
+///
+/// Amounts are kept as given and interpreted when the transaction is generated, so that
+/// amounts without a unit follow `amountUnit` of the transaction data.
+///
+/// # Variants
+///
+/// * `Exact` - An exact amount
+/// * `Text` - A decimal string with an optional unit, such as `"0.1 BTC"`, `"250000 sat"` or `"1.5 mBTC"`
+/// * `Number` - A JSON number, deprecated as it may not be exact
+#[derive(Debug, Clone, PartialEq)]
+pub enum BitcoinAmount {
+    Exact(Amount),
+    Text(String),
+    Number(f64),
+}
+
+impl BitcoinAmount {
+    /// Interprets the amount
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - Path of the amount in the input JSON, such as `outputs[0].amount`, for the error
+    /// * `default_unit` - Unit of the amounts given without a unit
+    ///
+    /// # Returns
+    ///
+    /// * `OfflineTransactionResult<Amount>` - The amount, or an error if the unit is unknown, or
+    ///   the amount is negative, not a number, or more precise than a satoshi
+    pub(crate) fn resolve(&self, field: &str, default_unit: Denomination) -> OfflineTransactionResult<Amount> {
+        let parse_error = |reason: String| ParseError {
+            from: field.to_string(),
+            to: "Amount".to_string(),
+            reason: format!("{:?}: {}", self.to_string(), reason),
+        };
+
+        match self {
+            BitcoinAmount::Exact(amount) => Ok(*amount),
+            BitcoinAmount::Text(text) => {
+                let (value, unit) = match text.split_whitespace().collect::<Vec<_>>()[..] {
+                    [value] => (value, default_unit),
+                    [value, unit] => (value, Denomination::from_str(unit).map_err(|e| parse_error(e.to_string()))?),
+                    _ => return Err(parse_error("expected an amount and an optional unit".to_string())),
+                };
+
+                Amount::from_str_in(value, unit).map_err(|e| parse_error(e.to_string()))
+            },
+            BitcoinAmount::Number(value) => {
+                Amount::from_float_in(*value, default_unit).map_err(|e| parse_error(e.to_string()))
+            },
+        }
+    }
+}
+
+impl BitcoinAmount {
+    /// Returns whether the amount was given as a JSON number, which is deprecated
+    pub(crate) fn is_number(&self) -> bool {
+        matches!(self, BitcoinAmount::Number(_))
+    }
+}
+
+/// Sums amounts without overflowing
+///
+/// # Arguments
+///
+/// * `amounts` - The amounts to sum
+/// * `what` - What the amounts are, such as `output amounts`, for the error
+///
+/// # Returns
+///
+/// * `OfflineTransactionResult<Amount>` - The total, or an error if it overflows or exceeds 21 million BTC
+pub(crate) fn checked_sum(amounts: impl IntoIterator<Item = Amount>, what: &str) -> OfflineTransactionResult<Amount> {
+    amounts.into_iter()
+        .try_fold(Amount::ZERO, Amount::checked_add)
+        .filter(|total| *total <= Amount::MAX_MONEY)
+        .ok_or_else(|| InvalidTransaction {
+            reason: format!("the total of the {} exceeds {}", what, Amount::MAX_MONEY.display_in(Denomination::Bitcoin).show_denomination()),
+        })
+}
+
+impl fmt::Display for BitcoinAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitcoinAmount::Exact(amount) => write!(f, "{} BTC", amount.to_string_in(Denomination::Bitcoin)),
+            BitcoinAmount::Text(text) => write!(f, "{}", text),
+            BitcoinAmount::Number(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl From<Amount> for BitcoinAmount {
+    fn from(amount: Amount) -> Self {
+        BitcoinAmount::Exact(amount)
+    }
+}
+
+/// Converts a BTC amount given as a floating point number
+///
+/// Deprecated compatibility path for the callers which passed amounts as `f64` before amounts
+/// were parsed exactly: the amount becomes a [`BitcoinAmount::Number`], which is rounded to the
+/// satoshi and warned about in the generation report. Give amounts as [`Amount`] or strings
+/// such as `"0.29 BTC"` instead. `#[deprecated]` has no effect on trait implementations, so the
+/// deprecation is reported at run time.
+impl From<f64> for BitcoinAmount {
+    fn from(value: f64) -> Self {
+        BitcoinAmount::Number(value)
+    }
+}
+
+impl From<&str> for BitcoinAmount {
+    fn from(text: &str) -> Self {
+        BitcoinAmount::Text(text.to_string())
+    }
+}
+
+impl From<String> for BitcoinAmount {
+    fn from(text: String) -> Self {
+        BitcoinAmount::Text(text)
+    }
+}
+
+impl Serialize for BitcoinAmount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BitcoinAmount::Number(value) => serializer.serialize_f64(*value),
+            amount => serializer.serialize_str(&amount.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BitcoinAmount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum JsonAmount {
+            Text(String),
+            Number(f64),
+        }
+
+        Ok(match JsonAmount::deserialize(deserializer)? {
+            JsonAmount::Text(text) => BitcoinAmount::Text(text),
+            JsonAmount::Number(value) => BitcoinAmount::Number(value),
+        })
+    }
+}
+
+/// (De)serializes a unit such as "BTC", "mBTC" or "sat"
+pub(crate) mod denomination {
+    use std::str::FromStr;
+    use bitcoin::Denomination;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(unit: &Denomination, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(unit)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Denomination, D::Error> {
+        let unit = String::deserialize(deserializer)?;
+
+        Denomination::from_str(unit.trim()).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_with_unit() {
+        let resolve = |text: &str| BitcoinAmount::from(text).resolve("amount", Denomination::Bitcoin);
+        assert_eq!(resolve("0.1 BTC").unwrap(), Amount::from_sat(10_000_000));
+        assert_eq!(resolve("250000 sat").unwrap(), Amount::from_sat(250_000));
+        assert_eq!(resolve("1.5 mBTC").unwrap(), Amount::from_sat(150_000));
+        assert_eq!(resolve(" 0.29 ").unwrap(), Amount::from_sat(29_000_000));
+        assert!(resolve("0.5 sat").is_err());
+        assert!(resolve("1 XBT").is_err());
+        assert!(resolve("1 BTC extra").is_err());
+        assert!(resolve("-1 BTC").is_err());
+    }
+
+    #[test]
+    fn test_resolve_with_default_unit() {
+        assert_eq!(BitcoinAmount::from("250000").resolve("amount", Denomination::Satoshi).unwrap(), Amount::from_sat(250_000));
+        assert_eq!(BitcoinAmount::Number(1.5).resolve("amount", Denomination::MilliBitcoin).unwrap(), Amount::from_sat(150_000));
+        assert_eq!(BitcoinAmount::from(Amount::ONE_SAT).resolve("amount", Denomination::MilliBitcoin).unwrap(), Amount::ONE_SAT);
+        assert!(BitcoinAmount::Number(f64::NAN).resolve("amount", Denomination::Bitcoin).is_err());
+    }
+
+    #[test]
+    fn test_checked_sum() {
+        let amounts = [Amount::from_sat(1), Amount::from_sat(2)];
+        assert_eq!(checked_sum(amounts, "amounts").unwrap(), Amount::from_sat(3));
+        assert!(checked_sum([Amount::MAX, Amount::ONE_SAT], "amounts").is_err());
+        assert!(checked_sum([Amount::MAX_MONEY, Amount::ONE_SAT], "amounts").is_err());
+    }
+
+    #[test]
+    fn test_serialize_exact_amount_with_unit() {
+        let json = serde_json::to_string(&BitcoinAmount::from(Amount::from_sat(29_000_000))).unwrap();
+        assert_eq!(json, r#""0.29 BTC""#);
+    }
+}
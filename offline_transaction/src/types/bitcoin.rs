@@ -1,4 +1,10 @@
+use bitcoin::{Denomination, Network};
 use serde::{Deserialize, Serialize};
+use crate::coin_selection::{CoinSelectionMethod, LONG_TERM_FEE_RATE};
+use crate::import::ImportTarget;
+use crate::transaction::change_split::ChangeSplit;
+use crate::transaction::ordering::OutputOrdering;
+use crate::types::amount::BitcoinAmount;
 
 
 /// Represents the data required for creating a Bitcoin transaction
@@ -8,35 +14,116 @@ use serde::{Deserialize, Serialize};
 ///
 /// # Fields
 ///
+/// * `network` - Network of the transaction, which every address must belong to (default: bitcoin)
 /// * `inputs` - Vector of transaction inputs containing previous transaction references and amounts
+/// * `utxos` - Pool of UTXOs to select the inputs from automatically when `inputs` is empty
+/// * `coin_selection` - Strategy to select the inputs from `utxos` (default: `auto`)
+/// * `long_term_fee_rate` - Fee rate in satoshis per byte expected to spend UTXOs later (default: 10)
 /// * `outputs` - Vector of transaction outputs specifying destination addresses and amounts
 /// * `change_address` - Address where remaining funds (after outputs and fees) will be sent
 /// * `private_key` - Private key used to sign the transaction
 /// * `fee_rate` - Fee rate in satoshis per byte for transaction fee calculation
+/// * `fee_target` - Confirmation target in blocks to estimate the fee rate from `fee_snapshot`
+/// * `fee_snapshot` - Path of a fee estimation snapshot file copied from the online environment
+/// * `max_fee_snapshot_age` - Maximum age in seconds of the fee snapshot (default: 3600)
+/// * `rbf` - Whether the inputs signal BIP125 replace-by-fee (default: `true`)
+/// * `lock_time` - Optional absolute lock time (block height or UNIX timestamp)
+/// * `original_transaction` - Signed transaction hex to replace when bumping its fee
+/// * `parent_transaction` - Transaction hex whose output is spent by a child-pays-for-parent transaction
+/// * `parent_fee` - Fee in satoshis paid by the parent transaction
+/// * `max_tx_weight` - Maximum weight of each consolidation transaction (default and upper bound: 400000)
+/// * `consolidation_address` - Address receiving consolidated UTXOs (default: `change_address`)
+/// * `frozen_utxos` - Path of the frozen UTXO registry whose UTXOs must not be spent
+/// * `change_split` - Split of the change into several outputs (default: a single change output)
+/// * `output_ordering` - Order of the inputs and outputs, and position of the change output (default: `append`)
+/// * `ordering_seed` - Seed of the random change position (default: derived from the private key and the transaction)
+/// * `amount_unit` - Unit of the amounts given without a unit (default: BTC)
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BitcoinTransactionData {
+    #[serde(default = "default_network", with = "crate::types::bitcoin_network")]
+    network: Network,
     inputs: Vec<BitcoinTransactionInput>,
+    #[serde(default)]
+    utxos: Vec<BitcoinTransactionInput>,
+    #[serde(rename = "coinSelection", default)]
+    coin_selection: CoinSelectionMethod,
+    #[serde(rename = "longTermFeeRate", default = "default_long_term_fee_rate")]
+    long_term_fee_rate: u64,
     outputs: Vec<BitcoinTransactionOutput>,
     #[serde(rename = "changeAddress")]
     change_address: String,
     #[serde(rename = "privateKey")]
     private_key: String,
-    #[serde(rename = "feeRate")]
-    fee_rate: u64,
+    #[serde(rename = "feeRate", default, skip_serializing_if = "Option::is_none")]
+    fee_rate: Option<u64>,
+    #[serde(rename = "feeTarget", default, skip_serializing_if = "Option::is_none")]
+    fee_target: Option<u16>,
+    #[serde(rename = "feeSnapshot", default, skip_serializing_if = "Option::is_none")]
+    fee_snapshot: Option<String>,
+    #[serde(rename = "maxFeeSnapshotAge", default = "default_max_fee_snapshot_age")]
+    max_fee_snapshot_age: u64,
+    #[serde(default = "default_rbf")]
+    rbf: bool,
+    #[serde(rename = "lockTime", default, skip_serializing_if = "Option::is_none")]
+    lock_time: Option<u32>,
+    #[serde(rename = "originalTransaction", default, skip_serializing_if = "Option::is_none")]
+    original_transaction: Option<String>,
+    #[serde(rename = "parentTransaction", default, skip_serializing_if = "Option::is_none")]
+    parent_transaction: Option<String>,
+    #[serde(rename = "parentFee", default, skip_serializing_if = "Option::is_none")]
+    parent_fee: Option<u64>,
+    #[serde(rename = "maxTxWeight", default, skip_serializing_if = "Option::is_none")]
+    max_tx_weight: Option<u64>,
+    #[serde(rename = "consolidationAddress", default, skip_serializing_if = "Option::is_none")]
+    consolidation_address: Option<String>,
+    #[serde(rename = "frozenUtxos", default, skip_serializing_if = "Option::is_none")]
+    frozen_utxos: Option<String>,
+    #[serde(rename = "changeSplit", default, skip_serializing_if = "Option::is_none")]
+    change_split: Option<ChangeSplit>,
+    #[serde(rename = "outputOrdering", default)]
+    output_ordering: OutputOrdering,
+    #[serde(rename = "orderingSeed", default, skip_serializing_if = "Option::is_none")]
+    ordering_seed: Option<u64>,
+    #[serde(rename = "amountUnit", default = "default_amount_unit", with = "crate::types::amount::denomination")]
+    amount_unit: Denomination,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+fn default_network() -> Network {
+    Network::Bitcoin
+}
+
+fn default_amount_unit() -> Denomination {
+    Denomination::Bitcoin
+}
+
+fn default_rbf() -> bool {
+    true
+}
+
+fn default_long_term_fee_rate() -> u64 {
+    LONG_TERM_FEE_RATE
+}
+
+fn default_max_fee_snapshot_age() -> u64 {
+    3600
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct BitcoinTransactionInput {
     txid: String,
     vout: u32,
-    amount: f64,
+    amount: BitcoinAmount,
     address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confirmations: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cluster: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct BitcoinTransactionOutput {
     address: String,
-    amount: f64,
+    amount: BitcoinAmount,
 }
 
 impl BitcoinTransactionData {
@@ -56,15 +143,53 @@ impl BitcoinTransactionData {
     /// A new BitcoinTransactionData instance with the specified parameters
     pub fn new(source_address: &str, private_key: &str) -> Self {
         Self {
+            network: default_network(),
             inputs: Vec::new(),
+            utxos: Vec::new(),
+            coin_selection: CoinSelectionMethod::default(),
+            long_term_fee_rate: default_long_term_fee_rate(),
             outputs: Vec::new(),
             change_address: source_address.to_string(),
             private_key: private_key.to_string(),
-            fee_rate: 1,
+            fee_rate: Some(1),
+            fee_target: None,
+            fee_snapshot: None,
+            max_fee_snapshot_age: default_max_fee_snapshot_age(),
+            rbf: true,
+            lock_time: None,
+            original_transaction: None,
+            parent_transaction: None,
+            parent_fee: None,
+            max_tx_weight: None,
+            consolidation_address: None,
+            frozen_utxos: None,
+            change_split: None,
+            output_ordering: OutputOrdering::default(),
+            ordering_seed: None,
+            amount_unit: default_amount_unit(),
         }
     }
     
 
+    /// Updates the network of the transaction
+    ///
+    /// If not set, the transaction is for the Bitcoin mainnet. Every address of the transaction
+    /// must belong to this network.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `network` - The network, such as `Network::Testnet4`
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_network(&mut self, network: Network) -> &mut Self {
+        self.network = network;
+
+        self
+    }
+
     /// Adds a new input to the transaction
     ///
     /// This method adds a new transaction input to the inputs vector. The input's source address
@@ -75,17 +200,19 @@ impl BitcoinTransactionData {
     ///
     /// * `txid` - The transaction ID of the UTXO to spend
     /// * `vout` - The output index in the referenced transaction
-    /// * `amount` - The amount in BTC contained in this input
+    /// * `amount` - The amount contained in this input, such as `Amount::from_sat(250_000)` or `"1.5 mBTC"`
     ///
     /// # Returns
     ///
     /// Returns a mutable reference to self to enable method chaining
-    pub fn push_input(&mut self, txid: &str, vout: u32, amount: f64) -> &mut Self {
+    pub fn push_input(&mut self, txid: &str, vout: u32, amount: impl Into<BitcoinAmount>) -> &mut Self {
         let new_input = BitcoinTransactionInput {
             txid: txid.to_string(),
             vout,
-            amount,
+            amount: amount.into(),
             address: self.change_address.clone(),
+            confirmations: None,
+            cluster: None,
         };
         self.inputs.push(new_input);
         
@@ -93,6 +220,105 @@ impl BitcoinTransactionData {
     }
     
 
+    /// Adds a new UTXO to the pool used for automatic coin selection
+    ///
+    /// The UTXO pool is only used when no input is added to the transaction, in which case
+    /// the inputs are selected from the pool to fund the outputs and the fee.
+    /// The UTXO's source address is automatically set to the change address specified during initialization.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `txid` - The transaction ID of the UTXO
+    /// * `vout` - The output index in the referenced transaction
+    /// * `amount` - The amount contained in this UTXO, such as `Amount::from_sat(250_000)` or `"1.5 mBTC"`
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn push_utxo(&mut self, txid: &str, vout: u32, amount: impl Into<BitcoinAmount>) -> &mut Self {
+        let new_utxo = BitcoinTransactionInput {
+            txid: txid.to_string(),
+            vout,
+            amount: amount.into(),
+            address: self.change_address.clone(),
+            confirmations: None,
+            cluster: None,
+        };
+        self.utxos.push(new_utxo);
+
+        self
+    }
+
+
+    /// Adds a UTXO of another address to the pool used for automatic coin selection
+    ///
+    /// Unlike [`BitcoinTransactionData::push_utxo`], the address of the UTXO is given, and the UTXO
+    /// can belong to a cluster (e.g. a customer) so that the `privacy` coin selection avoids
+    /// linking clusters on-chain. Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `txid` - The transaction ID of the UTXO
+    /// * `vout` - The output index in the referenced transaction
+    /// * `amount` - The amount contained in this UTXO, such as `Amount::from_sat(250_000)` or `"1.5 mBTC"`
+    /// * `address` - The address having the UTXO
+    /// * `cluster` - The cluster of the UTXO, if any
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn push_labeled_utxo(&mut self,
+                             txid: &str,
+                             vout: u32,
+                             amount: impl Into<BitcoinAmount>,
+                             address: &str,
+                             cluster: Option<&str>) -> &mut Self {
+        self.utxos.push(BitcoinTransactionInput::new(
+            txid, vout, amount, address, None, cluster.map(str::to_string)));
+
+        self
+    }
+
+
+    /// Updates the strategy used to select the inputs from the UTXO pool
+    ///
+    /// If not set, Branch-and-Bound with a single random draw fallback is used.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `coin_selection` - The coin selection strategy
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_coin_selection(&mut self, coin_selection: CoinSelectionMethod) -> &mut Self {
+        self.coin_selection = coin_selection;
+
+        self
+    }
+
+    /// Updates the fee rate expected to spend UTXOs in the long term
+    ///
+    /// The long term fee rate weighs the cost of a change output and the waste of a
+    /// coin selection. If not set, 10 satoshis per byte is used.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `long_term_fee_rate` - The long term fee rate in satoshis per byte
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_long_term_fee_rate(&mut self, long_term_fee_rate: u64) -> &mut Self {
+        self.long_term_fee_rate = long_term_fee_rate;
+
+        self
+    }
+
+
     /// Adds a new output to the transaction
     ///
     /// This method adds a new transaction output to the outputs vector.
@@ -101,15 +327,15 @@ impl BitcoinTransactionData {
     /// # Arguments
     ///
     /// * `address` - The destination address for this output
-    /// * `amount` - The amount in BTC to send to this address
+    /// * `amount` - The amount to send to this address, such as `Amount::from_sat(250_000)` or `"1.5 mBTC"`
     ///
     /// # Returns
     ///
     /// Returns a mutable reference to self to enable method chaining
-    pub fn push_output(&mut self, address: &str, amount: f64) -> &mut Self {
+    pub fn push_output(&mut self, address: &str, amount: impl Into<BitcoinAmount>) -> &mut Self {
         let new_output = BitcoinTransactionOutput {
             address: address.to_string(),
-            amount,
+            amount: amount.into(),
         };
         self.outputs.push(new_output);
         
@@ -131,15 +357,273 @@ impl BitcoinTransactionData {
     ///
     /// Returns a mutable reference to self to enable method chaining
     pub fn set_fee_rate(&mut self, fee_rate: u64) -> &mut Self {
-        self.fee_rate = fee_rate;
+        self.fee_rate = Some(fee_rate);
         
         self
     }
+
+    /// Estimates the fee rate from a fee snapshot file instead of a fixed fee rate
+    ///
+    /// The snapshot is a JSON file copied from the online environment which contains
+    /// `estimatesmartfee` results and/or a mempool fee histogram. When a confirmation
+    /// target is set, it takes precedence over the fixed fee rate.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `fee_target` - The confirmation target in blocks
+    /// * `fee_snapshot` - Path of the fee snapshot file
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_fee_target(&mut self, fee_target: u16, fee_snapshot: &str) -> &mut Self {
+        self.fee_target = Some(fee_target);
+        self.fee_snapshot = Some(fee_snapshot.to_string());
+
+        self
+    }
+
+    /// Updates the maximum accepted age of the fee snapshot
+    ///
+    /// Snapshots older than this age are refused. If not set, 3600 seconds is used.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_age` - The maximum age in seconds
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_max_fee_snapshot_age(&mut self, max_age: u64) -> &mut Self {
+        self.max_fee_snapshot_age = max_age;
+
+        self
+    }
     
+    /// Enables or disables BIP125 replace-by-fee signaling
+    ///
+    /// RBF signaling is enabled by default so that stuck transactions can be fee-bumped.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `rbf` - `true` to signal replaceability on every input, `false` to opt out
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_rbf(&mut self, rbf: bool) -> &mut Self {
+        self.rbf = rbf;
+
+        self
+    }
+
+    /// Sets the absolute lock time of the transaction
+    ///
+    /// Values below 500,000,000 are interpreted as a block height, larger values
+    /// as a UNIX timestamp. Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `lock_time` - The consensus encoded lock time
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_lock_time(&mut self, lock_time: u32) -> &mut Self {
+        self.lock_time = Some(lock_time);
+
+        self
+    }
+
+    /// Sets the signed transaction to be replaced by a fee bump
+    ///
+    /// The inputs of this data have to describe the outputs spent by the original
+    /// transaction (txid, vout, amount and address). Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw_transaction` - The original transaction encoded as a hexadecimal string
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_original_transaction(&mut self, raw_transaction: &str) -> &mut Self {
+        self.original_transaction = Some(raw_transaction.to_string());
+
+        self
+    }
+
+    /// Sets the stuck parent transaction to be accelerated by a child-pays-for-parent transaction
+    ///
+    /// The fee rate of this data is used as the target fee rate of the parent and child package.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw_transaction` - The parent transaction encoded as a hexadecimal string
+    /// * `fee` - The fee in satoshis paid by the parent transaction
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_parent_transaction(&mut self, raw_transaction: &str, fee: u64) -> &mut Self {
+        self.parent_transaction = Some(raw_transaction.to_string());
+        self.parent_fee = Some(fee);
+
+        self
+    }
+
+    /// Updates the maximum weight of each consolidation transaction
+    ///
+    /// The weight is capped by the standardness limit of 400000 weight units (100 kvB).
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_tx_weight` - The maximum weight in weight units
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_max_tx_weight(&mut self, max_tx_weight: u64) -> &mut Self {
+        self.max_tx_weight = Some(max_tx_weight);
+
+        self
+    }
+
+    /// Updates the address receiving the consolidated UTXOs
+    ///
+    /// If not set, the UTXOs are consolidated to the change address.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The consolidation address
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_consolidation_address(&mut self, address: &str) -> &mut Self {
+        self.consolidation_address = Some(address.to_string());
+
+        self
+    }
+
+    /// Updates the path of the frozen UTXO registry
+    ///
+    /// If not set, no UTXO is frozen. If set, generation fails when the registry cannot be read.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `frozen_utxos` - Path of the registry file
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_frozen_utxos(&mut self, frozen_utxos: &str) -> &mut Self {
+        self.frozen_utxos = Some(frozen_utxos.to_string());
+
+        self
+    }
+
+    /// Updates the split of the change into several outputs
+    ///
+    /// If not set, the change is sent to a single output.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `change_split` - The split of the change
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_change_split(&mut self, change_split: ChangeSplit) -> &mut Self {
+        self.change_split = Some(change_split);
+
+        self
+    }
+
+    /// Updates the unit of the amounts given without a unit
+    ///
+    /// If not set, such amounts are in BTC. Amounts with a unit such as `"250000 sat"` are not affected.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount_unit` - The unit
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_amount_unit(&mut self, amount_unit: Denomination) -> &mut Self {
+        self.amount_unit = amount_unit;
+
+        self
+    }
+
+    /// Updates the order of the inputs and outputs of the transaction
+    ///
+    /// If not set, the inputs and outputs are kept in request order and the change output is appended last.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_ordering` - The ordering
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_output_ordering(&mut self, output_ordering: OutputOrdering) -> &mut Self {
+        self.output_ordering = output_ordering;
+
+        self
+    }
+
+    /// Updates the seed of the random change position
+    ///
+    /// If not set, the seed is derived from the private key and the transaction so that the same
+    /// request always places the change output at the same position, which cannot be recomputed
+    /// from the broadcast transaction.
+    /// Uses the builder pattern to allow method chaining.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to self to enable method chaining
+    pub fn set_ordering_seed(&mut self, seed: u64) -> &mut Self {
+        self.ordering_seed = Some(seed);
+
+        self
+    }
+
+    pub(crate) fn network(&self) -> Network {
+        self.network
+    }
+
     pub(crate) fn inputs(&self) -> &Vec<BitcoinTransactionInput> {
         &self.inputs
     }
     
+    pub(crate) fn utxos(&self) -> &Vec<BitcoinTransactionInput> {
+        &self.utxos
+    }
+
+    pub(crate) fn coin_selection(&self) -> CoinSelectionMethod {
+        self.coin_selection
+    }
+
+    pub(crate) fn long_term_fee_rate(&self) -> u64 {
+        self.long_term_fee_rate
+    }
+
     pub(crate) fn outputs(&self) -> &Vec<BitcoinTransactionOutput> {
         &self.outputs
     }
@@ -152,12 +636,112 @@ impl BitcoinTransactionData {
         &self.private_key
     }
     
-    pub(crate) fn fee_rate(&self) -> u64 {
+    pub(crate) fn fee_rate(&self) -> Option<u64> {
         self.fee_rate
     }
+
+    pub(crate) fn fee_target(&self) -> Option<u16> {
+        self.fee_target
+    }
+
+    pub(crate) fn fee_snapshot(&self) -> Option<&str> {
+        self.fee_snapshot.as_deref()
+    }
+
+    pub(crate) fn max_fee_snapshot_age(&self) -> u64 {
+        self.max_fee_snapshot_age
+    }
+
+    pub(crate) fn rbf(&self) -> bool {
+        self.rbf
+    }
+
+    pub(crate) fn lock_time(&self) -> Option<u32> {
+        self.lock_time
+    }
+
+    pub(crate) fn original_transaction(&self) -> Option<&str> {
+        self.original_transaction.as_deref()
+    }
+
+    pub(crate) fn parent_transaction(&self) -> Option<&str> {
+        self.parent_transaction.as_deref()
+    }
+
+    pub(crate) fn parent_fee(&self) -> Option<u64> {
+        self.parent_fee
+    }
+
+    pub(crate) fn max_tx_weight(&self) -> Option<u64> {
+        self.max_tx_weight
+    }
+
+    pub(crate) fn consolidation_address(&self) -> Option<&str> {
+        self.consolidation_address.as_deref()
+    }
+
+    pub(crate) fn frozen_utxos(&self) -> Option<&str> {
+        self.frozen_utxos.as_deref()
+    }
+
+    pub(crate) fn change_split(&self) -> Option<&ChangeSplit> {
+        self.change_split.as_ref()
+    }
+
+    pub(crate) fn amount_unit(&self) -> Denomination {
+        self.amount_unit
+    }
+
+    pub(crate) fn output_ordering(&self) -> OutputOrdering {
+        self.output_ordering
+    }
+
+    pub(crate) fn ordering_seed(&self) -> Option<u64> {
+        self.ordering_seed
+    }
+
+    /// Returns the paths of the amounts given as JSON numbers, which are deprecated
+    pub(crate) fn number_amounts(&self) -> Vec<String> {
+        let inputs = [("inputs", &self.inputs), ("utxos", &self.utxos)].into_iter()
+            .flat_map(|(field, inputs)| inputs.iter()
+                .enumerate()
+                .filter(|(_, input)| input.amount().is_number())
+                .map(move |(index, _)| format!("{}[{}].amount", field, index)));
+        let outputs = self.outputs.iter()
+            .enumerate()
+            .filter(|(_, output)| output.amount().is_number())
+            .map(|(index, _)| format!("outputs[{}].amount", index));
+
+        inputs.chain(outputs)
+            .chain(self.change_split.iter().flat_map(ChangeSplit::number_amounts))
+            .collect()
+    }
+
+    pub(crate) fn push_imported(&mut self, target: ImportTarget, input: BitcoinTransactionInput) {
+        match target {
+            ImportTarget::Inputs => self.inputs.push(input),
+            ImportTarget::Utxos => self.utxos.push(input),
+        }
+    }
 }
 
 impl BitcoinTransactionInput {
+    pub(crate) fn new(txid: &str,
+                      vout: u32,
+                      amount: impl Into<BitcoinAmount>,
+                      address: &str,
+                      confirmations: Option<u32>,
+                      cluster: Option<String>) -> Self {
+        Self {
+            txid: txid.to_string(),
+            vout,
+            amount: amount.into(),
+            address: address.to_string(),
+            confirmations,
+            cluster,
+        }
+    }
+
     pub(crate) fn txid(&self) -> &str {
         &self.txid
     }
@@ -166,13 +750,21 @@ impl BitcoinTransactionInput {
         self.vout
     }
     
-    pub(crate) fn amount(&self) -> f64 {
-        self.amount
+    pub(crate) fn amount(&self) -> &BitcoinAmount {
+        &self.amount
     }
     
     pub(crate) fn address(&self) -> &str {
         &self.address
     }
+
+    pub(crate) fn confirmations(&self) -> Option<u32> {
+        self.confirmations
+    }
+
+    pub(crate) fn cluster(&self) -> Option<&str> {
+        self.cluster.as_deref()
+    }
 }
 
 impl BitcoinTransactionOutput {
@@ -180,7 +772,7 @@ impl BitcoinTransactionOutput {
         &self.address
     }
     
-    pub(crate) fn amount(&self) -> f64 {
-        self.amount
+    pub(crate) fn amount(&self) -> &BitcoinAmount {
+        &self.amount
     }
 }
@@ -1,8 +1,65 @@
+use std::str::FromStr;
 use serde::{Deserialize, Serialize};
+use crate::errors::{OfflineTransactionError, OfflineTransactionResult};
 
+pub mod amount;
 pub mod bitcoin;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct Network {
     pub(crate) network: String,
 }
+
+/// Names of the supported Bitcoin networks
+pub(crate) const BITCOIN_NETWORKS: [&str; 5] = ["bitcoin", "testnet", "testnet4", "signet", "regtest"];
+
+/// Parses the name of a Bitcoin network such as "bitcoin" or "testnet4"
+///
+/// # Arguments
+///
+/// * `network` - The network name, case insensitive
+///
+/// # Returns
+///
+/// * `OfflineTransactionResult<bitcoin::Network>` - The network, or an UnsupportedError for an unknown name
+pub fn parse_bitcoin_network(network: &str) -> OfflineTransactionResult<::bitcoin::Network> {
+    let network = network.to_lowercase();
+    match network.trim() {
+        name if BITCOIN_NETWORKS.contains(&name) => Ok(::bitcoin::Network::from_str(name)
+            .expect("the supported networks are known to bitcoin")),
+        name => Err(OfflineTransactionError::UnsupportedError {
+            component: "network".to_string(),
+            input: name.to_string(),
+            expected: BITCOIN_NETWORKS.join(", "),
+        }),
+    }
+}
+
+/// (De)serializes a Bitcoin network such as "bitcoin" or "testnet4"
+pub(crate) mod bitcoin_network {
+    use std::str::FromStr;
+    use bitcoin::Network;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(network: &Network, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(network)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Network, D::Error> {
+        let network = String::deserialize(deserializer)?;
+
+        Network::from_str(network.to_lowercase().trim()).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bitcoin_network() {
+        assert_eq!(parse_bitcoin_network(" Testnet4").unwrap(), ::bitcoin::Network::Testnet4);
+        assert_eq!(parse_bitcoin_network("bitcoin").unwrap(), ::bitcoin::Network::Bitcoin);
+        assert!(matches!(parse_bitcoin_network("litecoin"), Err(OfflineTransactionError::UnsupportedError { .. })));
+    }
+}
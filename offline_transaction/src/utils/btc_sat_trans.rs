@@ -1,33 +1,65 @@
+use bitcoin::Amount;
+use serde::{Deserialize, Deserializer};
+use crate::errors::OfflineTransactionError::ParseError;
+use crate::errors::OfflineTransactionResult;
+
 /// Converts Bitcoin (BTC) amount to Satoshis
 ///
+/// The amount is converted from its shortest decimal representation, so amounts such as
+/// 0.29 BTC are not truncated by the binary floating point error.
+///
 /// # Arguments
 ///
 /// * `btc` - Amount in Bitcoin to convert
 ///
 /// # Returns
 ///
-/// * Number of Satoshis as u64 (1 BTC = 100,000,000 Satoshis)
-pub(crate) fn btc_to_sat(btc: f64) -> u64 {
-    (btc * 100_000_000.0) as u64
+/// * Number of Satoshis as u64 (1 BTC = 100,000,000 Satoshis), or an error if the amount is
+///   negative, not a number, or has more than 8 decimals
+pub(crate) fn btc_to_sat(btc: f64) -> OfflineTransactionResult<u64> {
+    Amount::from_btc(btc)
+        .map(Amount::to_sat)
+        .map_err(|e| ParseError {
+            from: "f64".to_string(),
+            to: "Amount".to_string(),
+            reason: format!("{} BTC: {}", btc, e),
+        })
+}
+
+/// Deserializes an amount of BTC given as a number by external tools such as `bitcoin-cli`
+pub(crate) fn deserialize_btc_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+    let btc = f64::deserialize(deserializer)?;
+
+    btc_to_sat(btc)
+        .map(Amount::from_sat)
+        .map_err(serde::de::Error::custom)
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
     #[test]
     fn test_btc_to_sat() {
-        assert_eq!(btc_to_sat(1.0), 100_000_000);
+        assert_eq!(btc_to_sat(1.0).unwrap(), 100_000_000);
     }
-    
+
     #[test]
     fn test_btc_to_sat_2() {
-        assert_eq!(btc_to_sat(0.00000001), 1);
+        assert_eq!(btc_to_sat(0.00000001).unwrap(), 1);
     }
-    
+
     #[test]
     fn test_btc_to_sat_3() {
-        assert_eq!(btc_to_sat(0.15), 15_000_000);
+        assert_eq!(btc_to_sat(0.15).unwrap(), 15_000_000);
+    }
+
+    #[test]
+    fn test_btc_to_sat_is_not_truncated() {
+        // 0.29 * 100_000_000.0 is 28999999.999999996
+        assert_eq!(btc_to_sat(0.29).unwrap(), 29_000_000);
+        assert!(btc_to_sat(-0.1).is_err());
+        assert!(btc_to_sat(f64::NAN).is_err());
     }
 }
@@ -1,47 +1,283 @@
-use bitcoin::{TxIn, TxOut};
+use bitcoin::{Script, ScriptBuf, TxOut, VarInt};
+use crate::errors::OfflineTransactionError::InvalidTransaction;
 use crate::errors::OfflineTransactionResult;
 
+/// Dust relay fee rate (sat/vB) of the default relay policy (`-dustrelayfee`).
+pub(crate) const DUST_RELAY_FEE_RATE: u64 = 3;
 
-/// Calculates transaction fee based on inputs, outputs, change address and fee rate
+/// Maximum weight of a standard transaction (100 kvB).
+pub(crate) const MAX_STANDARD_TX_WEIGHT: u64 = 400_000;
+
+/// Minimum fee rate increase (sat/vB) a replacement must pay for its own relay (BIP125 rule 4).
+pub(crate) const INCREMENTAL_RELAY_FEE_RATE: u64 = 1;
+
+
+/// Estimates the weight of a signed input spending the given script_pubkey
+///
+/// The estimate assumes a 72 bytes signature and a 33 bytes compressed public key.
+/// P2SH outputs are assumed to be P2SH-wrapped P2WPKH, and unknown script types
+/// are estimated as P2PKH.
+///
+/// # Arguments
+///
+/// * `script_pubkey` - The script_pubkey of the spent output
+///
+/// # Returns
+///
+/// * `u64` - Estimated weight units of the input including its witness
+pub(crate) fn input_weight(script_pubkey: &Script) -> u64 {
+    // outpoint: 36 + script_sig length: 1 + sequence: 4
+    let base_size: u64 = 41;
+    // witness item count: 1 + signature: 1 + 72 + pubkey: 1 + 33
+    let p2wpkh_witness: u64 = 108;
+
+    if script_pubkey.is_p2wpkh() {
+        base_size * 4 + p2wpkh_witness
+    } else if script_pubkey.is_p2sh() {
+        // script_sig: push of the 22 bytes witness program
+        (base_size + 23) * 4 + p2wpkh_witness
+    } else if script_pubkey.is_p2tr() {
+        // witness item count: 1 + schnorr signature: 1 + 64
+        base_size * 4 + 66
+    } else {
+        // P2PKH script_sig: signature 1 + 72 + pubkey: 1 + 33
+        (base_size + 107) * 4
+    }
+}
+
+/// Calculates the weight of an output paying to the given script_pubkey
+///
+/// # Arguments
+///
+/// * `script_pubkey` - The script_pubkey of the output
+///
+/// # Returns
+///
+/// * `u64` - Weight units of the output
+pub(crate) fn output_weight(script_pubkey: &Script) -> u64 {
+    let script_len = script_pubkey.len() as u64;
+    // value: 8 + script length + script
+    (8 + VarInt(script_len).size() as u64 + script_len) * 4
+}
+
+/// Calculates the dust threshold of an output paying to the given script_pubkey
+///
+/// As in the default relay policy, an output is dust if its amount is lower than the fee to
+/// create and spend it at the dust relay fee rate, e.g. 294 sat for P2WPKH, 330 sat for P2TR
+/// and 546 sat for P2PKH. Unspendable OP_RETURN outputs have no threshold.
+///
+/// # Arguments
+///
+/// * `script_pubkey` - The script_pubkey of the output
+///
+/// # Returns
+///
+/// * `u64` - The smallest non-dust amount in satoshis
+pub(crate) fn dust_limit(script_pubkey: &Script) -> u64 {
+    if script_pubkey.is_op_return() {
+        return 0;
+    }
+
+    // outpoint: 36 + script_sig length: 1 + sequence: 4 + signature and public key: 107
+    let spend_size = if script_pubkey.is_witness_program() {
+        41 + 107 / 4
+    } else {
+        41 + 107
+    };
+
+    (output_weight(script_pubkey) / 4 + spend_size) * DUST_RELAY_FEE_RATE
+}
+
+/// Calculates the fee of a change output and the cost of change
+///
+/// The cost of change is the fee to create the change output now plus the fee to spend
+/// it later at the long term fee rate. Creating a change output smaller than that loses money.
+///
+/// # Arguments
+///
+/// * `change_script` - script_pubkey of the change output
+/// * `fee_rate` - Fee rate of the transaction (sat/vB)
+/// * `long_term_fee_rate` - Fee rate expected to spend the change later (sat/vB)
+///
+/// # Returns
+///
+/// * `OfflineTransactionResult<(u64, u64)>` - The change output fee and the cost of change in satoshis,
+///   or an error if the fee overflows
+pub(crate) fn change_cost(change_script: &Script,
+                          fee_rate: u64,
+                          long_term_fee_rate: u64) -> OfflineTransactionResult<(u64, u64)> {
+    let change_fee = output_vsize(change_script).checked_mul(fee_rate);
+    let spend_fee = input_vsize(change_script).checked_mul(long_term_fee_rate);
+
+    change_fee.zip(spend_fee)
+        .and_then(|(change_fee, spend_fee)| Some((change_fee, change_fee.checked_add(spend_fee)?)))
+        .ok_or_else(|| InvalidTransaction {
+            reason: format!("fee overflows at fee rate {} sat/vB", fee_rate.max(long_term_fee_rate)),
+        })
+}
+
+/// Estimates the virtual size of a signed input spending the given script_pubkey
+///
+/// The size is rounded up so that the sum over inputs never underestimates the transaction size.
+pub(crate) fn input_vsize(script_pubkey: &Script) -> u64 {
+    input_weight(script_pubkey).div_ceil(4)
+}
+
+/// Calculates the virtual size of an output paying to the given script_pubkey
+pub(crate) fn output_vsize(script_pubkey: &Script) -> u64 {
+    output_weight(script_pubkey).div_ceil(4)
+}
+
+/// Estimates the virtual size of a segwit transaction excluding its inputs
+///
+/// The size includes version, lock time, input and output counts (assuming less than 253 inputs),
+/// the segwit marker and flag, and the outputs. Adding [`input_vsize`] of every input never
+/// underestimates [`estimate_vsize`] of the whole transaction.
+pub(crate) fn base_vsize(outputs: &[TxOut]) -> u64 {
+    let weight = (8 + 1 + VarInt(outputs.len() as u64).size() as u64) * 4
+        + 2
+        + outputs.iter()
+            .map(|output| output_weight(&output.script_pubkey))
+            .sum::<u64>();
+
+    weight.div_ceil(4)
+}
+
+/// Estimates the virtual size of a signed transaction
+///
+/// # Arguments
+///
+/// * `input_scripts` - script_pubkeys of the outputs spent by the transaction
+/// * `outputs` - Outputs of the transaction
+///
+/// # Returns
+///
+/// * `u64` - Estimated virtual size in vbytes
+pub(crate) fn estimate_vsize(input_scripts: &[ScriptBuf], outputs: &[TxOut]) -> u64 {
+    estimate_weight(input_scripts, outputs).div_ceil(4)
+}
+
+/// Estimates the weight of a signed transaction
+///
+/// # Arguments
+///
+/// * `input_scripts` - script_pubkeys of the outputs spent by the transaction
+/// * `outputs` - Outputs of the transaction
+///
+/// # Returns
+///
+/// * `u64` - Estimated weight units
+pub(crate) fn estimate_weight(input_scripts: &[ScriptBuf], outputs: &[TxOut]) -> u64 {
+    // version: 4 + input count + output count + lock_time: 4
+    let mut weight = (8
+        + VarInt(input_scripts.len() as u64).size() as u64
+        + VarInt(outputs.len() as u64).size() as u64) * 4;
+
+    if input_scripts.iter().any(|script| !script.is_p2pkh()) {
+        // segwit marker and flag
+        weight += 2;
+    }
+
+    weight += input_scripts.iter()
+        .map(|script| input_weight(script))
+        .sum::<u64>();
+    weight += outputs.iter()
+        .map(|output| output_weight(&output.script_pubkey))
+        .sum::<u64>();
+
+    weight
+}
+
+/// Calculates transaction fee based on the spent scripts, outputs and fee rate
 ///
 /// This is an internal function that estimates the total transaction fee by calculating the virtual size
 /// of the transaction and multiplying it by the fee rate.
 ///
 /// # Arguments
 ///
-/// * `inputs` - Vector of transaction inputs
-/// * `outputs` - Vector of transaction outputs 
-/// * `change_address` - Address for returning change
+/// * `input_scripts` - script_pubkeys of the outputs spent by the transaction
+/// * `outputs` - Vector of transaction outputs
 /// * `fee_rate` - Fee rate in satoshis per virtual byte
 ///
 /// # Returns
 ///
-/// * `OfflineTransactionResult<u64>` - Calculated fee in satoshis or an error if address parsing fails
-pub(crate) fn fee_calculator(inputs: &[TxIn],
+/// * `OfflineTransactionResult<u64>` - Calculated fee in satoshis or an error if the fee overflows
+pub(crate) fn fee_calculator(input_scripts: &[ScriptBuf],
                              outputs: &[TxOut],
                              fee_rate: u64) -> OfflineTransactionResult<u64> {
-    let base_and_input_size_calc = |input: &TxIn| -> u64 {
-        // version: 4 + input: 1 + output: 1 + lock_time: 4
-        let mut tx_size: u64 = 10;
-        if input.script_sig.is_p2wpkh() {
-            // HRP: bc 2 words
-            tx_size += 2;
-            // P2WPKH: signature: about 72 bytes + pubkey: about 33 bytes
-            tx_size += (72 + 33) * inputs.len() as u64;
-        } else {
-            // P2PKH's input is around 148 bytes.
-            tx_size += 148 * inputs.len() as u64;
-        }
-        
-        tx_size
-    };
-    
-    let base_and_input_size = inputs.iter()
-        .map(|input| base_and_input_size_calc(input))
-        .sum::<u64>();
-    
-    // P2PKH's output is around 34 and P2WPKH's output is around 31 so it treats as 34.
-    let output_size = outputs.len() as u64 * 34;
-    
-    Ok((base_and_input_size + output_size) * fee_rate)
-}
\ No newline at end of file
+    estimate_vsize(input_scripts, outputs)
+        .checked_mul(fee_rate)
+        .ok_or_else(|| InvalidTransaction {
+            reason: format!("fee overflows at fee rate {} sat/vB", fee_rate),
+        })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::slice;
+    use std::str::FromStr;
+    use bitcoin::{Address, Amount};
+    use super::*;
+
+    fn script(address: &str) -> ScriptBuf {
+        Address::from_str(address).unwrap().assume_checked().script_pubkey()
+    }
+
+    #[test]
+    fn test_output_weight() {
+        assert_eq!(output_weight(&script("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")), 31 * 4);
+        assert_eq!(output_weight(&script("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH")), 34 * 4);
+    }
+
+    #[test]
+    fn test_dust_limit() {
+        assert_eq!(dust_limit(&script("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")), 294);
+        assert_eq!(dust_limit(&script("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0")), 330);
+        assert_eq!(dust_limit(&script("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH")), 546);
+        assert_eq!(dust_limit(&script("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy")), 540);
+        assert_eq!(dust_limit(&ScriptBuf::new_op_return([0u8; 4])), 0);
+    }
+
+    #[test]
+    fn test_change_cost() {
+        let p2wpkh = script("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        // 31 vbytes output at 2 sat/vB, 68 vbytes input at 10 sat/vB
+        assert_eq!(change_cost(&p2wpkh, 2, 10).unwrap(), (62, 742));
+        assert!(change_cost(&p2wpkh, u64::MAX, 10).is_err());
+    }
+
+    #[test]
+    fn test_estimate_vsize_p2wpkh() {
+        let p2wpkh = script("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        let outputs = vec![TxOut { value: Amount::ZERO, script_pubkey: p2wpkh.clone() }; 2];
+        // 1 input 2 outputs P2WPKH transaction is around 141 vbytes
+        assert_eq!(estimate_vsize(&[p2wpkh], &outputs), 141);
+    }
+
+    #[test]
+    fn test_fee_calculator_p2pkh() {
+        let p2pkh = script("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+        let outputs = vec![TxOut { value: Amount::ZERO, script_pubkey: p2pkh.clone() }];
+        // 10 + 148 + 34 = 192 vbytes
+        assert_eq!(fee_calculator(&[p2pkh], &outputs, 2).unwrap(), 384);
+    }
+
+    #[test]
+    fn test_estimate_vsize_plain_transactions() {
+        let p2pkh = script("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+        let p2wpkh = script("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        let outputs = |script: &ScriptBuf, count: usize| {
+            vec![TxOut { value: Amount::ZERO, script_pubkey: script.clone() }; count]
+        };
+
+        // P2PKH: 10 + 148 per input + 34 per output
+        assert_eq!(estimate_vsize(slice::from_ref(&p2pkh), &outputs(&p2pkh, 2)), 226);
+        assert_eq!(estimate_vsize(&[p2pkh.clone(), p2pkh.clone()], &outputs(&p2pkh, 1)), 340);
+        // P2WPKH: 10.5 + 68 per input + 31 per output, rounded up
+        assert_eq!(estimate_vsize(slice::from_ref(&p2wpkh), &outputs(&p2wpkh, 1)), 110);
+        assert_eq!(estimate_vsize(&[p2wpkh.clone(), p2wpkh.clone()], &outputs(&p2wpkh, 2)), 209);
+        // A P2PKH input in a segwit transaction pays for the marker and flag
+        assert_eq!(estimate_vsize(&[p2wpkh.clone(), p2pkh.clone()], &outputs(&p2pkh, 1)), 261);
+    }
+}
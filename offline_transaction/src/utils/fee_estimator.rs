@@ -0,0 +1,249 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use bitcoin::Amount;
+use serde::Deserialize;
+use crate::errors::OfflineTransactionError::{FutureFeeSnapshot, MissingParameter, ParseError, StaleFeeSnapshot, UnsupportedError};
+use crate::errors::OfflineTransactionResult;
+use crate::transaction::report::{FeeSnapshotReport, GenerationReport};
+use crate::types::bitcoin::BitcoinTransactionData;
+use crate::utils::read_file::read_file;
+
+/// Maximum virtual size of a block used to convert a confirmation target to mempool depth.
+const BLOCK_VSIZE: u64 = 1_000_000;
+
+/// Maximum number of seconds a fee snapshot may be dated in the future, as the clocks of the
+/// online and offline machines may drift apart.
+const MAX_FEE_SNAPSHOT_CLOCK_SKEW: u64 = 300;
+
+/// Fee estimation snapshot copied from the online environment
+///
+/// * `timestamp` - UNIX timestamp at which the snapshot was taken
+/// * `estimates` - `estimatesmartfee` results, `feerate` is in BTC/kvB
+/// * `fee_histogram` - Mempool fee histogram as pairs of fee rate (sat/vB) and virtual size
+#[derive(Debug, Deserialize)]
+pub(crate) struct FeeSnapshot {
+    timestamp: u64,
+    #[serde(default)]
+    estimates: Vec<SmartFeeEstimate>,
+    #[serde(rename = "feeHistogram", default)]
+    fee_histogram: Vec<(f64, u64)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SmartFeeEstimate {
+    blocks: u16,
+    feerate: Option<f64>,
+}
+
+/// Resolves the fee rate used to generate the transaction
+///
+/// If a confirmation target is specified, the fee rate is estimated from the fee snapshot,
+/// otherwise the fixed fee rate is used.
+///
+/// # Arguments
+///
+/// * `params` - The transaction data
+///
+/// # Returns
+///
+/// * `OfflineTransactionResult<GenerationReport>` - A report containing the fee rate and
+///   the snapshot used, or an error if:
+///   - Neither a fee rate nor a confirmation target is specified
+///   - The snapshot cannot be read or parsed
+///   - The snapshot is older than the maximum age
+///   - The snapshot is dated in the future beyond the clock skew
+///   - The snapshot has no estimate for the confirmation target
+pub(crate) fn resolve_fee_rate(params: &BitcoinTransactionData) -> OfflineTransactionResult<GenerationReport> {
+    let Some(fee_target) = params.fee_target() else {
+        return params.fee_rate()
+            .map(|fee_rate| GenerationReport::new(fee_rate, None))
+            .ok_or_else(|| MissingParameter {
+                parameter: "feeRate".to_string(),
+                operation: "calculate the transaction fee".to_string(),
+            });
+    };
+
+    let snapshot_path = params.fee_snapshot()
+        .ok_or_else(|| MissingParameter {
+            parameter: "feeSnapshot".to_string(),
+            operation: "estimate the fee rate for feeTarget".to_string(),
+        })?;
+    let snapshot: FeeSnapshot = serde_json::from_str(&read_file(Path::new(snapshot_path))?)
+        .map_err(|e| ParseError {
+            from: "Json".to_string(),
+            to: "FeeSnapshot".to_string(),
+            reason: e.to_string()
+        })?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    // A timestamp far ahead, such as one in milliseconds, would otherwise look fresh forever
+    let ahead = snapshot.timestamp.saturating_sub(now);
+    if ahead > MAX_FEE_SNAPSHOT_CLOCK_SKEW {
+        return Err(FutureFeeSnapshot {
+            path: snapshot_path.to_string(),
+            ahead,
+            max_skew: MAX_FEE_SNAPSHOT_CLOCK_SKEW,
+        });
+    }
+    let age = now.saturating_sub(snapshot.timestamp);
+    if age > params.max_fee_snapshot_age() {
+        return Err(StaleFeeSnapshot {
+            path: snapshot_path.to_string(),
+            age,
+            max_age: params.max_fee_snapshot_age(),
+        });
+    }
+
+    let (fee_rate, source) = snapshot.fee_rate_for(fee_target)?;
+
+    Ok(GenerationReport::new(fee_rate, Some(FeeSnapshotReport {
+        path: snapshot_path.to_string(),
+        timestamp: snapshot.timestamp,
+        age,
+        fee_target,
+        source: source.to_string(),
+    })))
+}
+
+impl FeeSnapshot {
+    /// Chooses the fee rate (sat/vB) for the confirmation target
+    ///
+    /// The `estimatesmartfee` result with the largest target not exceeding the requested
+    /// one is preferred. Otherwise, the fee rate is derived from the fee histogram as the
+    /// lowest fee rate still included in the next `fee_target` blocks.
+    fn fee_rate_for(&self, fee_target: u16) -> OfflineTransactionResult<(u64, &'static str)> {
+        let estimate = self.estimates.iter()
+            .filter(|estimate| estimate.blocks <= fee_target)
+            .filter_map(|estimate| estimate.feerate.map(|feerate| (estimate.blocks, feerate)))
+            .max_by_key(|(blocks, _)| *blocks);
+
+        if let Some((_, feerate)) = estimate {
+            let sat_per_kvb = Amount::from_btc(feerate)
+                .map_err(|e| ParseError {
+                    from: "feerate".to_string(),
+                    to: "Amount".to_string(),
+                    reason: e.to_string()
+                })?
+                .to_sat();
+            return Ok((sat_per_kvb.div_ceil(1000).max(1), "estimates"));
+        }
+
+        if !self.fee_histogram.is_empty() {
+            let mut histogram = self.fee_histogram.clone();
+            histogram.sort_by(|(rate_a, _), (rate_b, _)| rate_b.total_cmp(rate_a));
+
+            let capacity = BLOCK_VSIZE * fee_target as u64;
+            let mut cumulative_vsize: u64 = 0;
+            for (fee_rate, vsize) in histogram {
+                cumulative_vsize = cumulative_vsize.saturating_add(vsize);
+                if cumulative_vsize >= capacity {
+                    return Ok(((fee_rate.ceil() as u64).max(1), "feeHistogram"));
+                }
+            }
+            // The whole mempool is confirmed within the target
+            return Ok((1, "feeHistogram"));
+        }
+
+        Err(UnsupportedError {
+            component: "feeTarget".to_string(),
+            input: fee_target.to_string(),
+            expected: self.estimates.iter()
+                .filter(|estimate| estimate.feerate.is_some())
+                .map(|estimate| format!(">= {}", estimate.blocks))
+                .collect::<Vec<_>>()
+                .join(", "),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(json: &str) -> FeeSnapshot {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_fee_rate_from_estimates() {
+        let snapshot = snapshot(r#"{
+            "timestamp": 0,
+            "estimates": [
+                {"blocks": 2, "feerate": 0.00020000},
+                {"blocks": 6, "feerate": 0.00010001},
+                {"blocks": 12, "feerate": 0.00005000}
+            ]
+        }"#);
+        assert_eq!(snapshot.fee_rate_for(6).unwrap(), (11, "estimates"));
+        assert_eq!(snapshot.fee_rate_for(10).unwrap(), (11, "estimates"));
+        assert_eq!(snapshot.fee_rate_for(2).unwrap(), (20, "estimates"));
+        assert!(snapshot.fee_rate_for(1).is_err());
+    }
+
+    #[test]
+    fn test_fee_rate_from_histogram() {
+        let snapshot = snapshot(r#"{
+            "timestamp": 0,
+            "feeHistogram": [[5.0, 800000], [30.5, 600000], [12.0, 900000], [1.0, 5000000]]
+        }"#);
+        assert_eq!(snapshot.fee_rate_for(1).unwrap(), (12, "feeHistogram"));
+        assert_eq!(snapshot.fee_rate_for(2).unwrap(), (5, "feeHistogram"));
+        assert_eq!(snapshot.fee_rate_for(20).unwrap(), (1, "feeHistogram"));
+    }
+
+    #[test]
+    fn test_resolve_fee_rate_from_snapshot_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let snapshot_path = temp_dir.path().join("fee_snapshot.json");
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        std::fs::write(
+            &snapshot_path,
+            format!(r#"{{"timestamp": {}, "estimates": [{{"blocks": 6, "feerate": 0.00012}}]}}"#, now),
+        ).unwrap();
+
+        let mut params = BitcoinTransactionData::new("address", "key");
+        params.set_fee_target(6, snapshot_path.to_str().unwrap());
+        let report = resolve_fee_rate(&params).unwrap();
+        assert_eq!(report.fee_rate, 12);
+        assert_eq!(report.fee_snapshot.unwrap().timestamp, now);
+    }
+
+    #[test]
+    fn test_resolve_fee_rate_stale_snapshot() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let snapshot_path = temp_dir.path().join("fee_snapshot.json");
+        std::fs::write(
+            &snapshot_path,
+            r#"{"timestamp": 1700000000, "estimates": [{"blocks": 6, "feerate": 0.00012}]}"#,
+        ).unwrap();
+
+        let mut params = BitcoinTransactionData::new("address", "key");
+        params.set_fee_target(6, snapshot_path.to_str().unwrap());
+        assert!(matches!(resolve_fee_rate(&params), Err(StaleFeeSnapshot { .. })));
+    }
+
+    #[test]
+    fn test_resolve_fee_rate_future_snapshot() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let snapshot_path = temp_dir.path().join("fee_snapshot.json");
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let mut params = BitcoinTransactionData::new("address", "key");
+        params.set_fee_target(6, snapshot_path.to_str().unwrap());
+        let write_snapshot = |timestamp: u64| std::fs::write(
+            &snapshot_path,
+            format!(r#"{{"timestamp": {}, "estimates": [{{"blocks": 6, "feerate": 0.00012}}]}}"#, timestamp),
+        ).unwrap();
+
+        // A timestamp in milliseconds
+        write_snapshot(now * 1000);
+        assert!(matches!(resolve_fee_rate(&params), Err(FutureFeeSnapshot { .. })));
+
+        // A clock slightly behind the online machine is tolerated
+        write_snapshot(now + 60);
+        assert_eq!(resolve_fee_rate(&params).unwrap().fee_snapshot.unwrap().age, 0);
+    }
+}
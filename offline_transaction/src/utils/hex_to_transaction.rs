@@ -0,0 +1,25 @@
+use bitcoin::consensus::encode;
+use bitcoin::Transaction;
+use crate::errors::OfflineTransactionError::ParseError;
+use crate::errors::OfflineTransactionResult;
+
+/// Converts a hexadecimal string to a Bitcoin transaction
+///
+/// This is an internal function that decodes a consensus encoded transaction.
+/// If the decoding fails, it returns a ParseError with detailed information about the failure.
+///
+/// # Arguments
+///
+/// * `raw_transaction` - The transaction encoded as a hexadecimal string
+///
+/// # Returns
+///
+/// * `OfflineTransactionResult<Transaction>` - The decoded transaction or an error if decoding fails
+pub(crate) fn hex_to_transaction(raw_transaction: &str) -> OfflineTransactionResult<Transaction> {
+    encode::deserialize_hex(raw_transaction.trim())
+        .map_err(|e| ParseError {
+            from: "hex string".to_string(),
+            to: "Transaction".to_string(),
+            reason: e.to_string()
+        })
+}
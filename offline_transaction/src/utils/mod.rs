@@ -1,3 +1,7 @@
 pub(crate) mod btc_sat_trans;
 pub(crate) mod fee_calculator;
-pub(crate) mod str_to_address;
\ No newline at end of file
+pub(crate) mod fee_estimator;
+pub(crate) mod hex_to_transaction;
+pub(crate) mod read_file;
+pub(crate) mod str_to_address;
+pub(crate) mod wif_to_private_key;
\ No newline at end of file
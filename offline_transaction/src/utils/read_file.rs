@@ -0,0 +1,42 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use crate::errors::OfflineTransactionError::{FileNotFound, FileOperationError};
+use crate::errors::OfflineTransactionResult;
+
+/// Reads the whole content of a file as a string
+///
+/// Every file of the library and of the command line is read through this function,
+/// so a missing file is always reported as `FileNotFound`.
+///
+/// # Arguments
+///
+/// * `path` - Path to the file to read
+///
+/// # Returns
+///
+/// * `OfflineTransactionResult<String>` - The file content, or an error if:
+///   - File is not found
+///   - File cannot be opened or read
+pub fn read_file(path: &Path) -> OfflineTransactionResult<String> {
+    if !(path.exists() && path.is_file()) {
+        return Err(FileNotFound {
+            path: format!("{}", path.display())
+        });
+    }
+
+    let mut file = File::open(path)
+        .map_err(|e| FileOperationError {
+            operation: "open".to_string(),
+            reason: e.to_string(),
+        })?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .map_err(|e| FileOperationError {
+            operation: "read".to_string(),
+            reason: e.to_string(),
+        })?;
+
+    Ok(contents)
+}
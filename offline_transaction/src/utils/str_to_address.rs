@@ -1,28 +1,55 @@
 use std::str::FromStr;
-use bitcoin::Address;
+use bitcoin::{Address, Network};
 use crate::errors::OfflineTransactionError::ParseError;
 use crate::errors::OfflineTransactionResult;
 
-/// Converts a string to a Bitcoin address
+/// Converts a string to a Bitcoin address of the given network
 ///
-/// This is an internal function that attempts to parse a string into a Bitcoin address.
-/// If the parsing fails, it returns a ParseError with detailed information about the failure.
+/// This is an internal function that attempts to parse a string into a Bitcoin address and
+/// checks that the address belongs to the network of the transaction.
+/// If either fails, it returns a ParseError naming the field of the address.
 ///
 /// # Arguments
 ///
 /// * `address` - The string to be converted to a Bitcoin address
+/// * `network` - The network of the transaction
+/// * `field` - Path of the address in the input JSON, such as `outputs[0].address`, for the error
 ///
 /// # Returns
 ///
 /// * `OfflineTransactionResult<Address>` - The parsed Bitcoin address or an error if parsing fails
-pub(crate) fn str_to_address_unchecked(address: &str) -> OfflineTransactionResult<Address> {
-    let address = Address::from_str(address)
-        .map_err(|e| ParseError {
-            from: "string".to_string(),
-            to: "Address".to_string(),
-            reason: e.to_string()
-        })?
-        .assume_checked();
-    
-    Ok(address)
-}
\ No newline at end of file
+///   or the address belongs to another network
+pub(crate) fn str_to_address(address: &str, network: Network, field: &str) -> OfflineTransactionResult<Address> {
+    let parse_error = |reason: String| ParseError {
+        from: field.to_string(),
+        to: "Address".to_string(),
+        reason,
+    };
+
+    let unchecked = Address::from_str(address)
+        .map_err(|e| parse_error(format!("{:?}: {}", address, e)))?;
+    if !unchecked.is_valid_for_network(network) {
+        return Err(parse_error(format!("{:?} is not an address of the {} network", address, network)));
+    }
+
+    Ok(unchecked.assume_checked())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_str_to_address_checks_network() {
+        let mainnet = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        let testnet = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        assert!(str_to_address(mainnet, Network::Bitcoin, "changeAddress").is_ok());
+        assert!(str_to_address(testnet, Network::Testnet, "changeAddress").is_ok());
+        assert!(str_to_address(testnet, Network::Signet, "changeAddress").is_ok());
+        assert!(str_to_address(testnet, Network::Testnet4, "changeAddress").is_ok());
+        assert!(matches!(str_to_address(testnet, Network::Bitcoin, "outputs[1].address"),
+                         Err(ParseError { from, .. }) if from == "outputs[1].address"));
+        assert!(str_to_address(mainnet, Network::Regtest, "changeAddress").is_err());
+        assert!(str_to_address("invalid", Network::Bitcoin, "changeAddress").is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use bitcoin::{Network, NetworkKind, PrivateKey};
+use crate::errors::OfflineTransactionError::{InvalidTransaction, ParseError};
+use crate::errors::OfflineTransactionResult;
+
+/// Converts a WIF string to a private key of the given network
+///
+/// This is an internal function that decodes a private key in Wallet Import Format and checks
+/// that the key was encoded for the network of the transaction, as mainnet keys start with
+/// `5`, `K` or `L` and test network keys with `9` or `c`.
+///
+/// # Arguments
+///
+/// * `wif` - The private key in Wallet Import Format
+/// * `network` - The network of the transaction
+///
+/// # Returns
+///
+/// * `OfflineTransactionResult<PrivateKey>` - The private key, or an error if the WIF is invalid
+///   or encoded for another network
+pub(crate) fn wif_to_private_key(wif: &str, network: Network) -> OfflineTransactionResult<PrivateKey> {
+    let private_key = PrivateKey::from_wif(wif.trim())
+        .map_err(|e| ParseError {
+            from: "WIF string".to_string(),
+            to: "PrivateKey".to_string(),
+            reason: e.to_string()
+        })?;
+
+    let expected = NetworkKind::from(network);
+    if private_key.network != expected {
+        let kind = |kind: NetworkKind| if kind.is_mainnet() { "a mainnet" } else { "a test network" };
+        return Err(InvalidTransaction {
+            reason: format!("privateKey is {} key, but the network is {}", kind(private_key.network), network),
+        });
+    }
+
+    Ok(private_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAINNET_KEY: &str = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+    const TESTNET_KEY: &str = "cMahea7zqjxrtgAbB7LSGbcQUr1uX1ojuat9jZodMN87JcbXMTcA";
+
+    #[test]
+    fn test_wif_network_is_checked() {
+        assert!(wif_to_private_key(MAINNET_KEY, Network::Bitcoin).is_ok());
+        assert!(wif_to_private_key(TESTNET_KEY, Network::Testnet).is_ok());
+        assert!(wif_to_private_key(TESTNET_KEY, Network::Regtest).is_ok());
+        assert!(matches!(wif_to_private_key(TESTNET_KEY, Network::Bitcoin), Err(InvalidTransaction { .. })));
+        assert!(matches!(wif_to_private_key(MAINNET_KEY, Network::Signet), Err(InvalidTransaction { .. })));
+        assert!(matches!(wif_to_private_key("key", Network::Bitcoin), Err(ParseError { .. })));
+    }
+}
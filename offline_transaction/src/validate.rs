@@ -0,0 +1,405 @@
+use std::fmt;
+use std::str::FromStr;
+use bitcoin::{Amount, OutPoint, Txid};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use crate::errors::{OfflineTransactionError, OfflineTransactionResult};
+use crate::Platform;
+use crate::transaction::TransactionData;
+use crate::types::bitcoin::{BitcoinTransactionData, BitcoinTransactionInput, BitcoinTransactionOutput};
+use crate::utils::fee_calculator::dust_limit;
+use crate::utils::str_to_address::str_to_address;
+use crate::utils::wif_to_private_key::wif_to_private_key;
+
+/// A problem of the input JSON
+///
+/// # Fields
+///
+/// * `path` - Path of the offending field, such as `inputs[2].txid`, or empty for the whole JSON
+/// * `message` - What is wrong with the field
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationIssue {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.as_str() {
+            "" => write!(f, "{}", self.message),
+            path => write!(f, "{}: {}", path, self.message),
+        }
+    }
+}
+
+impl Platform {
+    /// Validates the input JSON, collecting every problem instead of stopping at the first one
+    ///
+    /// Every entry of `inputs`, `utxos` and `outputs` is parsed on its own so that a malformed
+    /// entry does not hide the problems of the others. Once the JSON is well-formed, the
+    /// transaction data is checked as by [`Platform::validate`].
+    ///
+    /// # Arguments
+    ///
+    /// * `json_str` - The input JSON
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<ValidationIssue>` - The problems found, empty if the JSON is valid
+    pub fn validate_json(json_str: &str) -> Vec<ValidationIssue> {
+        let mut json: Value = match serde_json::from_str(json_str) {
+            Ok(json) => json,
+            Err(e) => return vec![issue("", e.to_string())],
+        };
+
+        let mut issues = Vec::new();
+        if let Some(object) = json.as_object_mut() {
+            // Check the entries one by one, then the rest of the JSON without them
+            let inputs = object.insert("inputs".to_string(), Value::Array(Vec::new()));
+            let utxos = object.insert("utxos".to_string(), Value::Array(Vec::new()));
+            let outputs = object.insert("outputs".to_string(), Value::Array(Vec::new()));
+            issues.extend(entry_issues::<BitcoinTransactionInput>("inputs", inputs.as_ref(), true));
+            issues.extend(entry_issues::<BitcoinTransactionInput>("utxos", utxos.as_ref(), false));
+            issues.extend(entry_issues::<BitcoinTransactionOutput>("outputs", outputs.as_ref(), true));
+        }
+        if let Err(e) = Platform::json_parser(&json.to_string()) {
+            issues.insert(0, issue("", e.to_string()));
+        }
+
+        if !issues.is_empty() {
+            return issues;
+        }
+        match Platform::json_parser(json_str) {
+            Ok(platform) => platform.validate(),
+            Err(e) => vec![issue("", e.to_string())],
+        }
+    }
+
+    /// Validates the transaction data, collecting every problem instead of stopping at the first one
+    ///
+    /// Checks the txids, the addresses and their network, the amounts, duplicate outpoints,
+    /// dust outputs, the private key and the fee rate, and reports each problem with the JSON
+    /// path of the offending field.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<ValidationIssue>` - The problems found, empty if the transaction data is valid
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        match self {
+            Platform::Bitcoin(params) => {
+                let mut issues = Vec::new();
+                if params.inputs().is_empty() && params.utxos().is_empty() {
+                    issues.push(issue("inputs", "no inputs, and no UTXOs to select them from"));
+                }
+                issues.extend(input_issues(params, "inputs", params.inputs()));
+                issues.extend(input_issues(params, "utxos", params.utxos()));
+                issues.extend(output_issues(params));
+
+                if let Err(e) = str_to_address(params.change_address(), params.network(), "changeAddress") {
+                    issues.push(issue("changeAddress", reason(e)));
+                }
+                if let Some(address) = params.consolidation_address() {
+                    if let Err(e) = str_to_address(address, params.network(), "consolidationAddress") {
+                        issues.push(issue("consolidationAddress", reason(e)));
+                    }
+                }
+
+                if params.private_key().trim().is_empty() {
+                    issues.push(issue("privateKey", "the private key is missing"));
+                } else if let Err(e) = wif_to_private_key(params.private_key(), params.network()) {
+                    issues.push(issue("privateKey", reason(e)));
+                }
+
+                match (params.fee_target(), params.fee_snapshot(), params.fee_rate()) {
+                    (Some(_), None, _) => issues.push(issue("feeSnapshot", "feeSnapshot is required with feeTarget")),
+                    (None, _, None) => issues.push(issue("feeRate", "feeRate or feeTarget is required")),
+                    _ => {},
+                }
+
+                issues
+            }
+        }
+    }
+}
+
+/// Checks a batch of requests for inputs spending the same output in several transactions
+///
+/// Only one of the conflicting transactions can confirm, so a batch is expected to spend
+/// every output at most once. The unsigned transaction of every request is generated, so that
+/// the inputs selected from a shared `utxos` pool are compared rather than the whole pool.
+/// Duplicates within a request are reported by [`Platform::validate`].
+///
+/// # Arguments
+///
+/// * `requests` - The requests with a name identifying them, such as their file path
+///
+/// # Returns
+///
+/// * `Vec<ValidationIssue>` - An issue for every input spending an output spent by an earlier request,
+///   with the request name as a prefix of the path, and for every request which cannot be generated
+pub fn find_conflicts(requests: &[(String, Platform)]) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    // Outpoints spent by the earlier requests, with the path of their input
+    let mut spent: Vec<(OutPoint, String)> = Vec::new();
+
+    for (name, platform) in requests {
+        let transaction = match platform.generate_unsigned_transaction() {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                issues.push(issue(name, format!("cannot be generated: {}", e)));
+                continue;
+            }
+        };
+
+        let mut request_spent = Vec::new();
+        match (platform, transaction) {
+            (Platform::Bitcoin(params), TransactionData::BitcoinTransaction { raw_transaction, .. }) => {
+                for input in &raw_transaction.input {
+                    let outpoint = input.previous_output;
+                    let path = format!("{}:{}", name, input_path(params, &outpoint));
+                    if let Some((_, first)) = spent.iter().find(|(other, _)| *other == outpoint) {
+                        issues.push(issue(&path, format!("spends {} as {} does", outpoint, first)));
+                    }
+                    request_spent.push((outpoint, path));
+                }
+            }
+        }
+        spent.extend(request_spent);
+    }
+
+    issues
+}
+
+/// Path of the `inputs` or `utxos` entry a generated input spends
+fn input_path(params: &BitcoinTransactionData, outpoint: &OutPoint) -> String {
+    let spends = |input: &BitcoinTransactionInput| {
+        input.vout() == outpoint.vout && Txid::from_str(input.txid()).is_ok_and(|txid| txid == outpoint.txid)
+    };
+
+    match params.inputs().iter().position(spends) {
+        Some(index) => format!("inputs[{}]", index),
+        None => params.utxos().iter()
+            .position(spends)
+            .map_or_else(|| outpoint.to_string(), |index| format!("utxos[{}]", index)),
+    }
+}
+
+/// Parses every entry of an array of the input JSON on its own
+fn entry_issues<T: DeserializeOwned>(field: &str, entries: Option<&Value>, required: bool) -> Vec<ValidationIssue> {
+    match entries {
+        None if required => vec![issue(field, format!("missing field `{}`", field))],
+        None => Vec::new(),
+        Some(Value::Array(entries)) => entries.iter()
+            .enumerate()
+            .filter_map(|(index, entry)| T::deserialize(entry).err()
+                .map(|e| issue(&format!("{}[{}]", field, index), e.to_string())))
+            .collect(),
+        Some(_) => vec![issue(field, "expected an array")],
+    }
+}
+
+/// Checks the txid, the address and the amount of every input, and duplicate outpoints
+fn input_issues(params: &BitcoinTransactionData,
+                field: &str,
+                inputs: &[BitcoinTransactionInput]) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    // Outpoints of the inputs, None for an invalid txid
+    let mut outpoints: Vec<Option<(Txid, u32)>> = Vec::new();
+
+    for (index, input) in inputs.iter().enumerate() {
+        let path = format!("{}[{}]", field, index);
+        match Txid::from_str(input.txid()) {
+            Ok(txid) => {
+                let outpoint = Some((txid, input.vout()));
+                if let Some(first) = outpoints.iter().position(|other| *other == outpoint) {
+                    issues.push(issue(&path, format!("spends {}:{} as {}[{}] does", txid, input.vout(), field, first)));
+                }
+                outpoints.push(outpoint);
+            },
+            Err(e) => {
+                issues.push(issue(&format!("{}.txid", path), format!("{:?}: {}", input.txid(), e)));
+                outpoints.push(None);
+            },
+        }
+
+        if let Err(e) = str_to_address(input.address(), params.network(), &format!("{}.address", path)) {
+            issues.push(issue(&format!("{}.address", path), reason(e)));
+        }
+        issues.extend(amount_issues(&path, input.amount().resolve(&path, params.amount_unit()), None));
+    }
+
+    issues
+}
+
+/// Checks the address and the amount of every output
+fn output_issues(params: &BitcoinTransactionData) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    for (index, output) in params.outputs().iter().enumerate() {
+        let path = format!("outputs[{}]", index);
+        let script_pubkey = match str_to_address(output.address(), params.network(), &format!("{}.address", path)) {
+            Ok(address) => Some(address.script_pubkey()),
+            Err(e) => {
+                issues.push(issue(&format!("{}.address", path), reason(e)));
+                None
+            },
+        };
+        let dust = script_pubkey.map(|script_pubkey| dust_limit(&script_pubkey));
+        issues.extend(amount_issues(&path, output.amount().resolve(&path, params.amount_unit()), dust));
+    }
+
+    issues
+}
+
+/// Checks that an amount is valid, not zero and, for an output, not dust
+fn amount_issues(path: &str,
+                 amount: OfflineTransactionResult<Amount>,
+                 dust_limit: Option<u64>) -> Vec<ValidationIssue> {
+    let path = format!("{}.amount", path);
+    match (amount, dust_limit) {
+        (Err(e), _) => vec![issue(&path, reason(e))],
+        (Ok(Amount::ZERO), _) => vec![issue(&path, "the amount is zero")],
+        (Ok(amount), Some(dust_limit)) if amount.to_sat() < dust_limit => vec![issue(&path, format!(
+            "{} sat is under the dust limit of {} sat", amount.to_sat(), dust_limit))],
+        (Ok(_), _) => Vec::new(),
+    }
+}
+
+/// Returns the reason of an error without the field it was raised for
+fn reason(error: OfflineTransactionError) -> String {
+    match error {
+        OfflineTransactionError::ParseError { reason, .. } => reason,
+        error => error.to_string(),
+    }
+}
+
+fn issue(path: &str, message: impl Into<String>) -> ValidationIssue {
+    ValidationIssue { path: path.to_string(), message: message.into() }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coin_selection::CoinSelectionMethod;
+    use super::*;
+
+    const TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn paths(issues: &[ValidationIssue]) -> Vec<&str> {
+        issues.iter().map(|issue| issue.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_validate_collects_every_issue() {
+        let json = format!(r#"{{
+            "network": "bitcoin",
+            "inputs": [
+                {{"txid": "{txid}", "vout": 0, "amount": "0.01", "address": "{address}"}},
+                {{"txid": "not a txid", "vout": 0, "amount": "0.01", "address": "{address}"}},
+                {{"txid": "{txid}", "vout": 0, "amount": "0", "address": "{address}"}}
+            ],
+            "outputs": [
+                {{"address": "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", "amount": "0.001"}},
+                {{"address": "{address}", "amount": "100 sat"}}
+            ],
+            "changeAddress": "{address}",
+            "privateKey": "",
+            "feeRate": 1
+        }}"#, txid = TXID, address = ADDRESS);
+
+        let issues = Platform::validate_json(&json);
+        assert_eq!(paths(&issues), vec![
+            "inputs[1].txid",
+            "inputs[2]",
+            "inputs[2].amount",
+            "outputs[0].address",
+            "outputs[1].amount",
+            "privateKey",
+        ]);
+        assert_eq!(issues[1].to_string(), format!("inputs[2]: spends {}:0 as inputs[0] does", TXID));
+    }
+
+    #[test]
+    fn test_validate_malformed_entries() {
+        let json = format!(r#"{{
+            "network": "bitcoin",
+            "inputs": [
+                {{"txid": "{txid}", "amount": "0.01", "address": "{address}"}},
+                {{"txid": "{txid}", "vout": 1, "amount": "0.01", "address": "{address}"}}
+            ],
+            "outputs": [{{"address": "{address}"}}],
+            "privateKey": "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn",
+            "feeRate": 1
+        }}"#, txid = TXID, address = ADDRESS);
+
+        // The missing changeAddress is reported along with the malformed entries
+        let issues = Platform::validate_json(&json);
+        assert_eq!(paths(&issues), vec!["", "inputs[0]", "outputs[0]"]);
+        assert!(issues[0].message.contains("changeAddress"));
+        assert!(issues[1].message.contains("vout"));
+    }
+
+    #[test]
+    fn test_find_conflicts_across_requests() {
+        let request = |inputs: &[u32], coin_selection: CoinSelectionMethod| {
+            let mut data = BitcoinTransactionData::new(ADDRESS, "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn");
+            for vout in inputs {
+                data.push_input(TXID, *vout, "0.01");
+            }
+            // The requests share the pool of the wallet
+            data.push_utxo(TXID, 3, "0.01")
+                .push_utxo(TXID, 4, "0.5")
+                .push_output(ADDRESS, "0.005")
+                .set_coin_selection(coin_selection);
+            Platform::Bitcoin(data)
+        };
+        let requests = vec![
+            ("a.json".to_string(), request(&[0, 1], CoinSelectionMethod::Auto)),
+            // The pool is ignored when inputs are given
+            ("b.json".to_string(), request(&[2], CoinSelectionMethod::Auto)),
+            // Different UTXOs of the shared pool are selected
+            ("c.json".to_string(), request(&[], CoinSelectionMethod::SmallestFirst)),
+            ("d.json".to_string(), request(&[], CoinSelectionMethod::LargestFirst)),
+            ("e.json".to_string(), request(&[4, 1], CoinSelectionMethod::Auto)),
+        ];
+
+        let issues = find_conflicts(&requests);
+        assert_eq!(paths(&issues), vec!["e.json:inputs[0]", "e.json:inputs[1]"]);
+        assert_eq!(issues[0].message, format!("spends {}:4 as d.json:utxos[1] does", TXID));
+        assert_eq!(issues[1].message, format!("spends {}:1 as a.json:inputs[1] does", TXID));
+        assert!(find_conflicts(&requests[..4]).is_empty());
+
+        // The same selection from the shared pool conflicts
+        let requests = vec![
+            ("c.json".to_string(), request(&[], CoinSelectionMethod::SmallestFirst)),
+            ("f.json".to_string(), request(&[], CoinSelectionMethod::SmallestFirst)),
+        ];
+        let issues = find_conflicts(&requests);
+        assert_eq!(paths(&issues), vec!["f.json:utxos[0]"]);
+        assert_eq!(issues[0].message, format!("spends {}:3 as c.json:utxos[0] does", TXID));
+    }
+
+    #[test]
+    fn test_find_conflicts_reports_failed_generation() {
+        let data = BitcoinTransactionData::new(ADDRESS, "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn");
+        let issues = find_conflicts(&[("a.json".to_string(), Platform::Bitcoin(data))]);
+        assert_eq!(paths(&issues), vec!["a.json"]);
+        assert!(issues[0].message.starts_with("cannot be generated"));
+    }
+
+    #[test]
+    fn test_validate_valid_request() {
+        let json = format!(r#"{{
+            "network": "bitcoin",
+            "inputs": [{{"txid": "{txid}", "vout": 0, "amount": "0.01", "address": "{address}"}}],
+            "outputs": [{{"address": "{address}", "amount": "0.005"}}],
+            "changeAddress": "{address}",
+            "privateKey": "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn",
+            "feeRate": 1
+        }}"#, txid = TXID, address = ADDRESS);
+
+        assert!(Platform::validate_json(&json).is_empty());
+        assert_eq!(paths(&Platform::validate_json("{")), vec![""]);
+    }
+}
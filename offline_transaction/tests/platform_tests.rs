@@ -16,6 +16,14 @@ fn test_platform_from_json_success() {
     }
 }
 
+#[test]
+fn test_platform_from_json_string_amounts() {
+    // Test that amounts given as decimal strings are parsed as well as the legacy numbers
+    let json_path = Path::new("tests/data/sample_string_amounts.json");
+    let platform = Platform::from_json(json_path);
+    assert!(platform.is_ok(), "Platform::from_json should succeed with string amounts");
+}
+
 #[test]
 fn test_platform_from_json_file_not_found() {
     // Test that an error is returned when the file is not found
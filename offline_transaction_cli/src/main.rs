@@ -1,68 +1,511 @@
 mod utils;
 
-use std::fs::File;
-use std::io::Write;
 use std::path::PathBuf;
 use std::process::exit;
-use clap::Parser;
-use offline_transaction::errors::OfflineTransactionError;
-use offline_transaction::Platform;
-use crate::utils::display_offline_transaction_error;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use offline_transaction::coin_selection::CoinSelectionMethod;
+use offline_transaction::errors::{OfflineTransactionError, OfflineTransactionResult};
+use offline_transaction::frozen::FrozenRegistry;
+use offline_transaction::import::{ImportTarget, UtxoFilter};
+use offline_transaction::{read_file, Platform};
+use offline_transaction::transaction::decode::decode_raw_transaction;
+use offline_transaction::transaction::TransactionData;
+use offline_transaction::types::parse_bitcoin_network;
+use offline_transaction::validate::find_conflicts;
+use crate::utils::{display_generation_report, display_offline_transaction_error, display_policy_violations,
+                   display_transaction_summary, display_validation_issues, write_output, write_transaction};
 
 #[derive(Parser, Debug)]
-#[command(name = "offline_transaction", author, version, about, long_about = None)]
+#[command(name = "offline_transaction", author, version, about, long_about = None,
+          subcommand_negates_reqs = true)]
 pub struct Cli {
+    #[clap(short, long, required = true, help = "Path of the input JSON file")]
+    input: Option<PathBuf>,
+
+    #[clap(short, long, help = "Path of the output JSON file (Optional)")]
+    output: Option<PathBuf>,
+
+    #[clap(long, value_parser = parse_coin_selection,
+           help = "Coin selection strategy overriding 'coinSelection' of the input JSON \
+                   (auto, largestFirst, oldestFirst, smallestFirst, knapsack, privacy)")]
+    coin_selection: Option<CoinSelectionMethod>,
+
+    #[command(flatten)]
+    registry: RegistryArgs,
+
+    #[clap(long, global = true,
+           help = "Sign transactions breaking the relay policy (dust, weight, fee...) with a warning instead of refusing them")]
+    allow_nonstandard: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "Generate a BIP125 replacement paying a higher fee than the original transaction")]
+    Bump(IoArgs),
+    #[command(about = "Generate a child-pays-for-parent transaction accelerating a stuck parent transaction")]
+    Cpfp(IoArgs),
+    #[command(about = "Generate a BIP125 replacement cancelling the original transaction back to the change address")]
+    Cancel(IoArgs),
+    #[command(about = "Generate transactions consolidating the UTXO pool into a single address")]
+    Consolidate(IoArgs),
+    #[command(about = "Import UTXOs listed by `bitcoin-cli listunspent` or Esplora into the input JSON")]
+    Import(ImportArgs),
+    #[command(about = "Freeze a UTXO so that it is never spent")]
+    Freeze(FreezeArgs),
+    #[command(about = "Unfreeze a frozen UTXO")]
+    Unfreeze(UnfreezeArgs),
+    #[command(about = "List the frozen UTXOs")]
+    ListFrozen(RegistryArgs),
+    #[command(about = "Report every problem of the input JSON without generating a transaction")]
+    Validate(ValidateArgs),
+    #[command(about = "Decode raw transactions to JSON like `bitcoin-cli decoderawtransaction`")]
+    Decode(DecodeArgs),
+    #[command(about = "Check a batch of input JSON files for inputs spending the same output")]
+    Conflicts(ConflictsArgs),
+}
+
+#[derive(Args, Debug)]
+struct IoArgs {
     #[clap(short, long, help = "Path of the input JSON file")]
     input: PathBuf,
-    
-    #[clap(short, long, help = "Path of the output JSON file (Optional)")]   
+
+    #[clap(short, long, help = "Path of the output JSON file (Optional)")]
     output: Option<PathBuf>,
+
+    #[command(flatten)]
+    registry: RegistryArgs,
+}
+
+#[derive(Args, Debug)]
+struct ValidateArgs {
+    #[clap(short, long, help = "Path of the input JSON file")]
+    input: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct ConflictsArgs {
+    #[clap(short, long = "input", required = true, num_args = 1.., help = "Paths of the input JSON files")]
+    inputs: Vec<PathBuf>,
+
+    #[command(flatten)]
+    registry: RegistryArgs,
+}
+
+#[derive(Args, Debug)]
+struct DecodeArgs {
+    #[clap(short, long, help = "Path of the file of raw transactions in hex, one per line")]
+    input: PathBuf,
+
+    #[clap(long, default_value = "bitcoin",
+           help = "Network the output addresses are displayed for (bitcoin, testnet, testnet4, signet, regtest)")]
+    network: String,
+
+    #[clap(short, long, help = "Path of the output JSON file (Optional)")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct ImportArgs {
+    #[clap(short, long, help = "Path of the input JSON file to import the UTXOs into")]
+    input: PathBuf,
+
+    #[clap(short, long, help = "Path of the saved UTXO JSON")]
+    utxos: PathBuf,
+
+    #[clap(long, value_enum, default_value_t = UtxoFormat::Listunspent, help = "Format of the UTXO JSON")]
+    format: UtxoFormat,
+
+    #[clap(long, required_if_eq("format", "esplora"),
+           help = "Address the Esplora UTXOs belong to (required with --format esplora)")]
+    utxo_address: Option<String>,
+
+    #[clap(long, help = "Chain tip height when the Esplora UTXOs were saved, to count their confirmations")]
+    tip_height: Option<u32>,
+
+    #[clap(long, default_value_t = 0, help = "Skip UTXOs with fewer confirmations")]
+    min_confirmations: u32,
+
+    #[clap(long = "address", help = "Only import UTXOs of this address (can be repeated)")]
+    addresses: Vec<String>,
+
+    #[clap(long, help = "Add the UTXOs to 'inputs' instead of the 'utxos' pool")]
+    as_inputs: bool,
+
+    #[clap(short, long, help = "Path of the updated input JSON file (Optional)")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct RegistryArgs {
+    #[clap(short, long, default_value = "frozen_utxos.json",
+           help = "Path of the frozen UTXO registry, consulted by every generated transaction")]
+    registry: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct FreezeArgs {
+    #[command(flatten)]
+    registry: RegistryArgs,
+
+    #[clap(value_parser = parse_outpoint, help = "UTXO to freeze as <txid>:<vout>")]
+    outpoint: (String, u32),
+
+    #[clap(long, help = "Why the UTXO is frozen (Optional)")]
+    reason: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct UnfreezeArgs {
+    #[command(flatten)]
+    registry: RegistryArgs,
+
+    #[clap(value_parser = parse_outpoint, help = "UTXO to unfreeze as <txid>:<vout>")]
+    outpoint: (String, u32),
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum UtxoFormat {
+    #[value(help = "`bitcoin-cli listunspent` result")]
+    Listunspent,
+    #[value(help = "Esplora or mempool.space `/address/:address/utxo` response")]
+    Esplora,
 }
 
 fn main() {
     let cli_args = Cli::parse();
-    
-    let input_json_path = cli_args.input;
-    let output_path = cli_args.output;
-    
-    let platform = Platform::from_json(input_json_path)
+
+    match cli_args.command {
+        Some(Command::Bump(io_args)) => {
+            let platform = load_generation_platform(io_args.input, &io_args.registry);
+            run(platform, io_args.output, cli_args.allow_nonstandard, Platform::generate_fee_bump_transaction)
+        },
+        Some(Command::Cpfp(io_args)) => {
+            let platform = load_generation_platform(io_args.input, &io_args.registry);
+            run(platform, io_args.output, cli_args.allow_nonstandard, Platform::generate_cpfp_transaction)
+        },
+        Some(Command::Cancel(io_args)) => {
+            let platform = load_generation_platform(io_args.input, &io_args.registry);
+            run(platform, io_args.output, cli_args.allow_nonstandard, Platform::generate_cancel_transaction)
+        },
+        Some(Command::Consolidate(io_args)) => {
+            let platform = load_generation_platform(io_args.input, &io_args.registry);
+            consolidate(platform, io_args.output, cli_args.allow_nonstandard)
+        },
+        Some(Command::Import(import_args)) => {
+            import(import_args)
+        },
+        Some(Command::Freeze(freeze_args)) => {
+            freeze(freeze_args)
+        },
+        Some(Command::Unfreeze(unfreeze_args)) => {
+            unfreeze(unfreeze_args)
+        },
+        Some(Command::ListFrozen(registry_args)) => {
+            list_frozen(registry_args)
+        },
+        Some(Command::Validate(validate_args)) => {
+            validate(validate_args)
+        },
+        Some(Command::Decode(decode_args)) => {
+            decode(decode_args)
+        },
+        Some(Command::Conflicts(conflicts_args)) => {
+            conflicts(conflicts_args)
+        },
+        None => {
+            // `input` is required when no subcommand is given.
+            let input_json_path = cli_args.input.expect("input is required");
+            let mut platform = load_generation_platform(input_json_path, &cli_args.registry);
+            if let Some(coin_selection) = cli_args.coin_selection {
+                match &mut platform {
+                    Platform::Bitcoin(data) => {
+                        data.set_coin_selection(coin_selection);
+                    }
+                }
+            }
+            run(platform, cli_args.output, cli_args.allow_nonstandard, Platform::generate_unsigned_transaction)
+        }
+    }
+}
+
+/// Parses the `--coin-selection` argument
+fn parse_coin_selection(value: &str) -> Result<CoinSelectionMethod, String> {
+    value.parse::<CoinSelectionMethod>().map_err(|e| e.to_string())
+}
+
+/// Parses an outpoint given as `<txid>:<vout>`
+fn parse_outpoint(value: &str) -> Result<(String, u32), String> {
+    let (txid, vout) = value.rsplit_once(':')
+        .ok_or_else(|| format!("expected <txid>:<vout>, got {}", value))?;
+    let vout = vout.parse::<u32>().map_err(|e| format!("invalid vout {}: {}", vout, e))?;
+
+    Ok((txid.to_string(), vout))
+}
+
+/// Loads the platform from the input JSON, exiting on error
+fn load_platform(input_json_path: PathBuf) -> Platform {
+    Platform::from_json(input_json_path)
+        .unwrap_or_else(|e| {
+            display_offline_transaction_error(e);
+            exit(1);
+        })
+}
+
+/// Loads the platform of a transaction to generate from the input JSON, exiting on error
+///
+/// The registry of `--registry` replaces `frozenUtxos` of the input JSON, so that a UTXO frozen
+/// with `freeze` is never spent. Generation fails if the registry cannot be read.
+fn load_generation_platform(input_json_path: PathBuf, registry_args: &RegistryArgs) -> Platform {
+    let mut platform = load_platform(input_json_path);
+    match &mut platform {
+        Platform::Bitcoin(data) => {
+            data.set_frozen_utxos(&registry_args.registry.to_string_lossy());
+        }
+    }
+
+    platform
+}
+
+/// Generates and signs a transaction from the platform, and then writes it out
+///
+/// Transactions breaking the relay policy are not signed unless nonstandard transactions are allowed.
+fn run(platform: Platform,
+       output_path: Option<PathBuf>,
+       allow_nonstandard: bool,
+       generate: fn(&Platform) -> OfflineTransactionResult<TransactionData>) {
+    let mut transaction = generate(&platform)
         .unwrap_or_else(|e| {
             display_offline_transaction_error(e);
             exit(1);
         });
-    let raw_transaction = platform.generate_unsigned_transaction()
+    display_generation_report(transaction.report());
+    if !display_policy_violations(&transaction.check_policy(), allow_nonstandard) {
+        exit(1);
+    }
+
+    let signed_transaction = transaction.sign_transaction()
         .unwrap_or_else(|e| {
             display_offline_transaction_error(e);
-            exit(1);       
-        })
-        .sign_transaction()
+            exit(1);
+        });
+    let summary = signed_transaction.summary()
         .unwrap_or_else(|e| {
             display_offline_transaction_error(e);
             exit(1);
-        })
-        .get_raw_transaction();
-    
-    if let Some(output_path) = output_path {
-        let mut output_file = File::create(&output_path)
-            .map_err(|e| OfflineTransactionError::FileOperationError {
-                operation: "open".to_string(),
-                reason: e.to_string(),
-            })
+        });
+    display_transaction_summary(&summary);
+    let raw_transaction = signed_transaction.get_raw_transaction();
+
+    write_transaction(&raw_transaction, output_path)
+        .unwrap_or_else(|e| {
+            display_offline_transaction_error(e);
+            exit(1);
+        });
+}
+
+/// Generates and signs the consolidation transactions, and then writes them out one per line
+fn consolidate(platform: Platform, output_path: Option<PathBuf>, allow_nonstandard: bool) {
+    let transactions = platform.generate_consolidation_transactions()
+        .unwrap_or_else(|e| {
+            display_offline_transaction_error(e);
+            exit(1);
+        });
+
+    let mut raw_transactions = Vec::new();
+    for mut transaction in transactions {
+        display_generation_report(transaction.report());
+        if !display_policy_violations(&transaction.check_policy(), allow_nonstandard) {
+            exit(1);
+        }
+
+        let signed_transaction = transaction.sign_transaction()
             .unwrap_or_else(|e| {
                 display_offline_transaction_error(e);
                 exit(1);
             });
-        output_file.write_fmt(format_args!("{}", raw_transaction))
-            .map_err(|e| OfflineTransactionError::FileOperationError {
-                operation: "write".to_string(),
-                reason: e.to_string(),
-            })
+        let summary = signed_transaction.summary()
             .unwrap_or_else(|e| {
                 display_offline_transaction_error(e);
                 exit(1);
             });
-        println!("Transaction written to {}", output_path.as_path().display());
+        display_transaction_summary(&summary);
+        let raw_transaction = signed_transaction.get_raw_transaction();
+        raw_transactions.push(raw_transaction);
+    }
+
+    write_output(&raw_transactions.join("\n"), "Transactions", output_path)
+        .unwrap_or_else(|e| {
+            display_offline_transaction_error(e);
+            exit(1);
+        });
+}
+
+/// Imports the UTXOs into the input JSON, and then writes the updated JSON out
+fn import(import_args: ImportArgs) {
+    let mut platform = load_platform(import_args.input);
+
+    let mut filter = UtxoFilter::new();
+    filter.set_min_confirmations(import_args.min_confirmations);
+    for address in &import_args.addresses {
+        filter.push_address(address);
+    }
+    let target = if import_args.as_inputs { ImportTarget::Inputs } else { ImportTarget::Utxos };
+
+    let imported = read_file(&import_args.utxos)
+        .and_then(|json| match (&mut platform, import_args.format) {
+            (Platform::Bitcoin(data), UtxoFormat::Listunspent) => {
+                data.import_listunspent(&json, &filter, target)
+            },
+            (Platform::Bitcoin(data), UtxoFormat::Esplora) => {
+                // `utxo_address` is required with the esplora format.
+                let address = import_args.utxo_address.as_deref().expect("utxo address is required");
+                data.import_esplora(&json, address, import_args.tip_height, &filter, target)
+            },
+        })
+        .and_then(|count| platform.to_json().map(|json| (count, json)));
+
+    match imported {
+        Ok((count, json)) => {
+            eprintln!("Imported {} UTXOs", count);
+            write_output(&json, "Input JSON", import_args.output)
+                .unwrap_or_else(|e| {
+                    display_offline_transaction_error(e);
+                    exit(1);
+                });
+        },
+        Err(e) => {
+            display_offline_transaction_error(e);
+            exit(1);
+        }
+    }
+}
+
+/// Freezes a UTXO in the registry, creating the registry if it does not exist
+fn freeze(freeze_args: FreezeArgs) {
+    let path = freeze_args.registry.registry;
+    let (txid, vout) = freeze_args.outpoint;
+    let frozen = FrozenRegistry::load_or_new(&path)
+        .and_then(|mut registry| {
+            let frozen = registry.freeze(&txid, vout, freeze_args.reason.as_deref())?;
+            registry.save(&path).map(|_| frozen)
+        })
+        .unwrap_or_else(|e| {
+            display_offline_transaction_error(e);
+            exit(1);
+        });
+
+    if frozen {
+        println!("Frozen {}:{}", txid, vout);
     } else {
-        println!("{}", raw_transaction);
+        println!("{}:{} is already frozen", txid, vout);
     }
 }
+
+/// Unfreezes a UTXO of the registry
+fn unfreeze(unfreeze_args: UnfreezeArgs) {
+    let path = unfreeze_args.registry.registry;
+    let (txid, vout) = unfreeze_args.outpoint;
+    let unfrozen = FrozenRegistry::load(&path)
+        .and_then(|mut registry| {
+            let unfrozen = registry.unfreeze(&txid, vout)?;
+            registry.save(&path).map(|_| unfrozen)
+        })
+        .unwrap_or_else(|e| {
+            display_offline_transaction_error(e);
+            exit(1);
+        });
+
+    if unfrozen {
+        println!("Unfrozen {}:{}", txid, vout);
+    } else {
+        println!("{}:{} is not frozen", txid, vout);
+    }
+}
+
+/// Lists the frozen UTXOs of the registry
+fn list_frozen(registry_args: RegistryArgs) {
+    let registry = FrozenRegistry::load_or_new(&registry_args.registry)
+        .unwrap_or_else(|e| {
+            display_offline_transaction_error(e);
+            exit(1);
+        });
+
+    for utxo in registry.utxos() {
+        match utxo.reason() {
+            Some(reason) => println!("{}:{} (frozen at {}): {}", utxo.txid(), utxo.vout(), utxo.frozen_at(), reason),
+            None => println!("{}:{} (frozen at {})", utxo.txid(), utxo.vout(), utxo.frozen_at()),
+        }
+    }
+}
+
+/// Validates the input JSON, and then displays every problem found
+fn validate(validate_args: ValidateArgs) {
+    let json = read_file(&validate_args.input)
+        .unwrap_or_else(|e| {
+            display_offline_transaction_error(e);
+            exit(1);
+        });
+
+    let issues = Platform::validate_json(&json);
+    if issues.is_empty() {
+        println!("{} is valid", validate_args.input.display());
+        return;
+    }
+
+    display_validation_issues(&issues);
+    exit(1);
+}
+
+/// Checks the input JSON files for inputs spending the same output, and then displays the conflicts found
+fn conflicts(conflicts_args: ConflictsArgs) {
+    let requests = conflicts_args.inputs.into_iter()
+        .map(|path| (path.display().to_string(), load_generation_platform(path, &conflicts_args.registry)))
+        .collect::<Vec<_>>();
+
+    let issues = find_conflicts(&requests);
+    if issues.is_empty() {
+        println!("No conflicting inputs in {} requests", requests.len());
+        return;
+    }
+
+    display_validation_issues(&issues);
+    exit(1);
+}
+
+/// Decodes the raw transactions, and then writes them out as JSON
+///
+/// A single transaction is written as an object, several ones as an array.
+fn decode(decode_args: DecodeArgs) {
+    let decoded = parse_bitcoin_network(&decode_args.network)
+        .and_then(|network| {
+            let raw_transactions = read_file(&decode_args.input)?;
+            raw_transactions.lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|raw_transaction| decode_raw_transaction(raw_transaction, network))
+                .collect::<OfflineTransactionResult<Vec<_>>>()
+        })
+        .and_then(|decoded| {
+            let json = match decoded.as_slice() {
+                [transaction] => serde_json::to_string_pretty(transaction),
+                transactions => serde_json::to_string_pretty(transactions),
+            };
+            json.map_err(|e| OfflineTransactionError::ParseError {
+                from: "DecodedTransaction".to_string(),
+                to: "Json".to_string(),
+                reason: e.to_string(),
+            })
+        })
+        .unwrap_or_else(|e| {
+            display_offline_transaction_error(e);
+            exit(1);
+        });
+
+    write_output(&decoded, "Decoded transactions", decode_args.output)
+        .unwrap_or_else(|e| {
+            display_offline_transaction_error(e);
+            exit(1);
+        });
+}
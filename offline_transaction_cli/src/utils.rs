@@ -1,25 +1,189 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
 use colored::Colorize;
-use offline_transaction::errors::OfflineTransactionError;
+use offline_transaction::errors::{OfflineTransactionError, OfflineTransactionResult};
+use offline_transaction::transaction::policy::PolicyViolation;
+use offline_transaction::transaction::report::GenerationReport;
+use offline_transaction::transaction::summary::TransactionSummary;
+use offline_transaction::validate::ValidationIssue;
 
 pub(crate) fn display_offline_transaction_error(error: OfflineTransactionError) {
     match &error {
         OfflineTransactionError::FileNotFound {..} => {
-            eprintln!("{}: {}", "FileNotFound".bold().red(), error.to_string())
+            eprintln!("{}: {}", "FileNotFound".bold().red(), error)
         },
         OfflineTransactionError::FileOperationError {..} => {
-            eprintln!("{}: {}", "FileOperationError".bold().red(), error.to_string())
+            eprintln!("{}: {}", "FileOperationError".bold().red(), error)
         },
         OfflineTransactionError::InvalidFile {..} => {
-            eprintln!("{}: {}", "InvalidFile".bold().red(), error.to_string())
+            eprintln!("{}: {}", "InvalidFile".bold().red(), error)
         },
         OfflineTransactionError::ParseError {..} => {
-            eprintln!("{}: {}", "ParseError".bold().red(), error.to_string())
+            eprintln!("{}: {}", "ParseError".bold().red(), error)
         },
         OfflineTransactionError::HasherError(_) => {
-            eprintln!("{}: {}", "HasherError".bold().red(), error.to_string())
+            eprintln!("{}: {}", "HasherError".bold().red(), error)
         },
         OfflineTransactionError::UnsupportedError {..} => {
-            eprintln!("{}: {}", "UnsupportedError".bold().red(), error.to_string())
+            eprintln!("{}: {}", "UnsupportedError".bold().red(), error)
+        },
+        OfflineTransactionError::MissingParameter {..} => {
+            eprintln!("{}: {}", "MissingParameter".bold().red(), error)
+        },
+        OfflineTransactionError::InvalidTransaction {..} => {
+            eprintln!("{}: {}", "InvalidTransaction".bold().red(), error)
+        },
+        OfflineTransactionError::StaleFeeSnapshot {..} => {
+            eprintln!("{}: {}", "StaleFeeSnapshot".bold().red(), error)
+        },
+        OfflineTransactionError::FutureFeeSnapshot {..} => {
+            eprintln!("{}: {}", "FutureFeeSnapshot".bold().red(), error)
+        },
+        OfflineTransactionError::FrozenUtxo {..} => {
+            eprintln!("{}: {}", "FrozenUtxo".bold().red(), error)
+        },
+        OfflineTransactionError::InsufficientFunds {..} => {
+            eprintln!("{}: {}", "InsufficientFunds".bold().red(), error)
+        }
+    }
+}
+
+/// Writes the transaction hex to the output file, or displays it on Stdout if no file is specified
+pub(crate) fn write_transaction(raw_transaction: &str,
+                                output_path: Option<PathBuf>) -> OfflineTransactionResult<()> {
+    write_output(raw_transaction, "Transaction", output_path)
+}
+
+/// Writes the contents to the output file, or displays them on Stdout if no file is specified
+pub(crate) fn write_output(contents: &str,
+                           description: &str,
+                           output_path: Option<PathBuf>) -> OfflineTransactionResult<()> {
+    if let Some(output_path) = output_path {
+        let mut output_file = File::create(&output_path)
+            .map_err(|e| OfflineTransactionError::FileOperationError {
+                operation: "open".to_string(),
+                reason: e.to_string(),
+            })?;
+        output_file.write_fmt(format_args!("{}", contents))
+            .map_err(|e| OfflineTransactionError::FileOperationError {
+                operation: "write".to_string(),
+                reason: e.to_string(),
+            })?;
+        println!("{} written to {}", description, output_path.as_path().display());
+    } else {
+        println!("{}", contents);
+    }
+
+    Ok(())
+}
+
+/// Displays the generation report on Stderr so that Stdout only contains the transaction
+pub(crate) fn display_generation_report(report: &GenerationReport) {
+    match &report.fee_snapshot {
+        Some(snapshot) => {
+            eprintln!("{}: {} sat/vB (target {} blocks, {} from {} taken at {}, {} seconds old)",
+                      "Fee rate".bold(), report.fee_rate, snapshot.fee_target, snapshot.source,
+                      snapshot.path, snapshot.timestamp, snapshot.age)
+        },
+        None => {
+            eprintln!("{}: {} sat/vB", "Fee rate".bold(), report.fee_rate)
+        }
+    }
+
+    if let Some(coin_selection) = &report.coin_selection {
+        eprintln!("{}: {} with {} ({}, waste {} sat)", "Coin selection".bold(), coin_selection.strategy,
+                  coin_selection.algorithm, coin_selection.reason, coin_selection.waste);
+        for utxo in &coin_selection.selected {
+            eprintln!("  - {}:{} {} sat (effective value {} sat)",
+                      utxo.txid, utxo.vout, utxo.amount, utxo.effective_value);
+        }
+        eprintln!("{}: {}", "Clusters".bold(), coin_selection.clusters.join(", "));
+        if coin_selection.clusters.len() > 1 {
+            eprintln!("{}: the selected UTXOs merge the clusters {}, which links them on-chain",
+                      "WARNING".bold().yellow(), coin_selection.clusters.join(", "));
+        }
+
+        eprintln!("{}:", "Waste comparison".bold());
+        for comparison in &coin_selection.comparison {
+            match (comparison.waste, comparison.inputs, comparison.changeless) {
+                (Some(waste), Some(inputs), Some(changeless)) => {
+                    eprintln!("  - {}: {} sat ({} inputs, {})", comparison.strategy, waste, inputs,
+                              if changeless { "changeless" } else { "with change" })
+                },
+                _ => eprintln!("  - {}: insufficient funds", comparison.strategy),
+            }
         }
     }
-}
\ No newline at end of file
+
+    if let Some(dropped_change) = &report.dropped_change {
+        eprintln!("{}: {} sat added to the fee ({})", "Change dropped".bold(),
+                  dropped_change.amount, dropped_change.reason);
+    }
+
+    for amount in &report.amounts {
+        eprintln!("{}: {} = {} sat", amount.field.bold(), amount.given, amount.sat);
+    }
+
+    for warning in &report.warnings {
+        eprintln!("{}: {}", "WARNING".bold().yellow(), warning);
+    }
+
+    if !report.frozen_utxos.is_empty() {
+        eprintln!("{}: {}", "Frozen UTXOs skipped".bold(), report.frozen_utxos.join(", "));
+    }
+
+    match report.change_indices.as_slice() {
+        [] => {},
+        [change_index] => eprintln!("{}: {}", "Change index".bold(), change_index),
+        change_indices => eprintln!("{}: {}", "Change indices".bold(), change_indices.iter()
+            .map(|index| index.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")),
+    }
+
+    if let Some(consolidation) = &report.consolidation {
+        eprintln!("{}: transaction {} of {}, {} UTXOs, {} WU", "Consolidation".bold(), consolidation.part,
+                  consolidation.parts, consolidation.inputs, consolidation.weight);
+        // Uneconomical UTXOs are the same for every part
+        if consolidation.part == 1 {
+            for utxo in &consolidation.uneconomical {
+                eprintln!("  - skipped uneconomical {}:{} {} sat (effective value {} sat)",
+                          utxo.txid, utxo.vout, utxo.amount, utxo.effective_value);
+            }
+        }
+    }
+}
+
+/// Displays the summary of the transaction on Stderr so that Stdout only contains the transaction
+pub(crate) fn display_transaction_summary(summary: &TransactionSummary) {
+    eprintln!("{}: {}", "Txid".bold(), summary.txid);
+    eprintln!("{}: {}", "Wtxid".bold(), summary.wtxid);
+    eprintln!("{}: {} bytes, {} vB, {} WU{}", "Size".bold(), summary.size, summary.vsize, summary.weight,
+              if summary.estimated { " (estimated)" } else { "" });
+    eprintln!("{}: {} sat in, {} sat out, fee {} sat ({:.2} sat/vB)", "Amounts".bold(),
+              summary.total_in, summary.total_out, summary.fee, summary.fee_rate);
+}
+
+/// Displays the relay policy violations of the transaction on Stderr
+///
+/// Returns whether the transaction may be signed: it is standard, or nonstandard transactions are allowed.
+pub(crate) fn display_policy_violations(violations: &[PolicyViolation], allow_nonstandard: bool) -> bool {
+    for violation in violations {
+        if allow_nonstandard {
+            eprintln!("{}: {}: {}", "WARNING".bold().yellow(), violation.rule, violation.reason);
+        } else {
+            eprintln!("{}: {}: {}", "PolicyViolation".bold().red(), violation.rule, violation.reason);
+        }
+    }
+
+    violations.is_empty() || allow_nonstandard
+}
+
+/// Displays the problems of the input JSON on Stderr
+pub(crate) fn display_validation_issues(issues: &[ValidationIssue]) {
+    for issue in issues {
+        eprintln!("{}: {}", "Invalid".bold().red(), issue);
+    }
+    eprintln!("{} problems found", issues.len());
+}